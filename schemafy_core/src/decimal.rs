@@ -0,0 +1,233 @@
+//! (De)serializes `rust_decimal::Decimal` as a JSON number without going through `f64` where the
+//! digits are available, that is from strings and from the numbers of `serde_json` with the
+//! `arbitrary_precision` feature. Generated fields refer to it through
+//! `#[serde(with = "schemafy_core::decimal")]`.
+
+use std::{fmt, str::FromStr};
+
+use rust_decimal::Decimal;
+use serde::{de, ser, Deserialize, Deserializer, Serialize, Serializer};
+
+/// The key of the map which `serde_json` represents numbers as with the `arbitrary_precision`
+/// feature, which may be enabled by another crate of the build.
+const NUMBER_TOKEN: &str = "$serde_json::private::Number";
+
+/// A type which is represented as a JSON number.
+pub trait DecimalNumber: Sized {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer;
+
+    fn deserialize<'de, D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>;
+}
+
+impl DecimalNumber for Decimal {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        // Keeps every digit if `serde_json` has the `arbitrary_precision` feature and writes the
+        // closest `f64` otherwise
+        serde_json::Number::from_str(&self.to_string())
+            .map_err(ser::Error::custom)?
+            .serialize(serializer)
+    }
+
+    fn deserialize<'de, D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_any(DecimalVisitor)
+    }
+}
+
+impl<T> DecimalNumber for Option<T>
+where
+    T: DecimalNumber,
+{
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match self {
+            Some(value) => value.serialize(serializer),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    fn deserialize<'de, D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct Value<T>(T);
+
+        impl<'de, T> Deserialize<'de> for Value<T>
+        where
+            T: DecimalNumber,
+        {
+            fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+            where
+                D: Deserializer<'de>,
+            {
+                T::deserialize(deserializer).map(Value)
+            }
+        }
+
+        Ok(Option::<Value<T>>::deserialize(deserializer)?.map(|value| value.0))
+    }
+}
+
+struct DecimalVisitor;
+
+impl DecimalVisitor {
+    fn parse<E>(self, value: &str) -> Result<Decimal, E>
+    where
+        E: de::Error,
+    {
+        Decimal::from_str(value)
+            .or_else(|_| Decimal::from_scientific(value))
+            .map_err(|_| E::invalid_value(de::Unexpected::Str(value), &self))
+    }
+}
+
+impl<'de> de::Visitor<'de> for DecimalVisitor {
+    type Value = Decimal;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter.write_str("a decimal number or a string holding one")
+    }
+
+    fn visit_i64<E>(self, value: i64) -> Result<Decimal, E>
+    where
+        E: de::Error,
+    {
+        Ok(value.into())
+    }
+
+    fn visit_u64<E>(self, value: u64) -> Result<Decimal, E>
+    where
+        E: de::Error,
+    {
+        Ok(value.into())
+    }
+
+    fn visit_f64<E>(self, value: f64) -> Result<Decimal, E>
+    where
+        E: de::Error,
+    {
+        // The shortest digits which read back as `value`, such as `0.1` rather than the
+        // digits of the closest `f64`
+        Decimal::from_str(&value.to_string())
+            .map_err(|_| E::invalid_value(de::Unexpected::Float(value), &self))
+    }
+
+    fn visit_str<E>(self, value: &str) -> Result<Decimal, E>
+    where
+        E: de::Error,
+    {
+        self.parse(value)
+    }
+
+    fn visit_map<A>(self, mut map: A) -> Result<Decimal, A::Error>
+    where
+        A: de::MapAccess<'de>,
+    {
+        match map.next_key::<String>()? {
+            Some(key) if key == NUMBER_TOKEN => self.parse(&map.next_value::<String>()?),
+            _ => Err(de::Error::invalid_type(de::Unexpected::Map, &self)),
+        }
+    }
+}
+
+pub fn serialize<T, S>(value: &T, serializer: S) -> Result<S::Ok, S::Error>
+where
+    T: DecimalNumber,
+    S: Serializer,
+{
+    value.serialize(serializer)
+}
+
+pub fn deserialize<'de, T, D>(deserializer: D) -> Result<T, D::Error>
+where
+    T: DecimalNumber,
+    D: Deserializer<'de>,
+{
+    T::deserialize(deserializer)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::{Deserialize, Serialize};
+
+    use super::*;
+
+    #[derive(Debug, PartialEq, Deserialize, Serialize)]
+    struct Invoice {
+        #[serde(with = "crate::decimal")]
+        price: Decimal,
+        #[serde(default, with = "crate::decimal")]
+        discount: Option<Decimal>,
+    }
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    #[serde(untagged)]
+    enum Total {
+        Amount(Invoice),
+        Missing(String),
+    }
+
+    fn decimal(s: &str) -> Decimal {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn round_trip() {
+        let invoice: Invoice = serde_json::from_str(r#"{ "price": 19.99 }"#).unwrap();
+        assert_eq!(
+            invoice,
+            Invoice {
+                price: decimal("19.99"),
+                discount: None,
+            }
+        );
+        let json = r#"{"price":19.99,"discount":2}"#;
+        let invoice: Invoice = serde_json::from_str(json).unwrap();
+        assert_eq!(invoice.discount, Some(decimal("2")));
+        assert_eq!(serde_json::to_string(&invoice).unwrap(), json);
+
+        let invoice: Invoice =
+            serde_json::from_str(r#"{ "price": "12345678901234.123456789" }"#).unwrap();
+        assert_eq!(invoice.price, decimal("12345678901234.123456789"));
+        assert_eq!(
+            serde_json::from_str::<Invoice>(r#"{ "price": "1.5e3" }"#)
+                .unwrap()
+                .price,
+            decimal("1500")
+        );
+        assert!(serde_json::from_str::<Invoice>(r#"{ "price": "a" }"#).is_err());
+        assert!(serde_json::from_str::<Invoice>(r#"{ "price": {} }"#).is_err());
+    }
+
+    #[test]
+    fn untagged() {
+        let total: Total = serde_json::from_str(r#"{ "price": 0.1 }"#).unwrap();
+        assert_eq!(
+            total,
+            Total::Amount(Invoice {
+                price: decimal("0.1"),
+                discount: None,
+            })
+        );
+    }
+
+    #[cfg(feature = "arbitrary_precision")]
+    #[test]
+    fn keeps_digits() {
+        let json = r#"{"price":12345678901234.123456789,"discount":0.10}"#;
+        let invoice: Invoice = serde_json::from_str(json).unwrap();
+        assert_eq!(invoice.price, decimal("12345678901234.123456789"));
+        assert_eq!(serde_json::to_string(&invoice).unwrap(), json);
+    }
+}
@@ -1 +1,21 @@
-pub mod one_or_many;
+#[cfg(feature = "decimal")]
+pub mod decimal;
+pub mod number;
+pub mod one_or_many;
+#[cfg(feature = "time")]
+pub mod rfc3339;
+pub mod uri;
+pub mod validate;
+pub mod validator;
+
+// The crates of the types which `format`s and numbers are generated as
+#[cfg(feature = "chrono")]
+pub use chrono;
+#[cfg(feature = "decimal")]
+pub use rust_decimal;
+#[cfg(feature = "time")]
+pub use time;
+#[cfg(feature = "url")]
+pub use url;
+#[cfg(feature = "uuid")]
+pub use uuid;
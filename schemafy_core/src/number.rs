@@ -0,0 +1,81 @@
+//! A JSON number which keeps the digits it was written with when the `arbitrary_precision`
+//! feature is enabled, such as monetary values which `f64` can not represent exactly.
+
+use std::{fmt, ops::Deref, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+
+/// A number backed by `serde_json::Number`. Without the `arbitrary_precision` feature it holds
+/// an `i64`, `u64` or `f64` like `serde_json::Number` does.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(transparent)]
+pub struct Number(pub serde_json::Number);
+
+impl Deref for Number {
+    type Target = serde_json::Number;
+
+    fn deref(&self) -> &serde_json::Number {
+        &self.0
+    }
+}
+
+impl From<serde_json::Number> for Number {
+    fn from(number: serde_json::Number) -> Number {
+        Number(number)
+    }
+}
+
+impl From<Number> for serde_json::Number {
+    fn from(number: Number) -> serde_json::Number {
+        number.0
+    }
+}
+
+impl From<i64> for Number {
+    fn from(number: i64) -> Number {
+        Number(number.into())
+    }
+}
+
+impl From<u64> for Number {
+    fn from(number: u64) -> Number {
+        Number(number.into())
+    }
+}
+
+impl fmt::Display for Number {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl FromStr for Number {
+    type Err = serde_json::Error;
+
+    fn from_str(s: &str) -> Result<Number, serde_json::Error> {
+        serde_json::from_str(s)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let number: Number = serde_json::from_str("12.5").unwrap();
+        assert_eq!(number.as_f64(), Some(12.5));
+        assert_eq!(serde_json::to_string(&number).unwrap(), "12.5");
+        assert_eq!("-3".parse::<Number>().unwrap(), Number::from(-3i64));
+        assert!("\"3\"".parse::<Number>().is_err());
+    }
+
+    #[cfg(feature = "arbitrary_precision")]
+    #[test]
+    fn keeps_digits() {
+        let json = "1234567890.12345678901234567890";
+        let number: Number = json.parse().unwrap();
+        assert_eq!(number.to_string(), json);
+        assert_eq!(serde_json::to_string(&number).unwrap(), json);
+    }
+}
@@ -24,6 +24,13 @@ where
             formatter.write_str("one or many")
         }
 
+        fn visit_bool<E>(self, value: bool) -> Result<Vec<T>, E>
+        where
+            E: de::Error,
+        {
+            Deserialize::deserialize(value.into_deserializer()).map(|v| vec![v])
+        }
+
         fn visit_i64<E>(self, value: i64) -> Result<Vec<T>, E>
         where
             E: de::Error,
@@ -101,6 +108,11 @@ mod tests {
         assert_eq!(from_str::<OneOrMany<i32>>("1").unwrap(), OneOrMany(vec![1]));
     }
 
+    #[test]
+    fn deserialize_one_bool() {
+        assert_eq!(from_str::<OneOrMany<bool>>("true").unwrap(), OneOrMany(vec![true]));
+    }
+
     #[test]
     fn deserialize_many_int() {
         assert_eq!(
@@ -0,0 +1,152 @@
+//! (De)serializes the `time` types of the `date-time` and `date` formats as RFC 3339 strings, as
+//! `time` uses a format of its own. Generated fields refer to it through
+//! `#[serde(with = "schemafy_core::rfc3339")]`.
+
+use serde::{de, ser, Deserialize, Deserializer, Serializer};
+use time::{
+    format_description::{well_known::Rfc3339 as DateTime, FormatItem},
+    macros::format_description,
+    Date, OffsetDateTime,
+};
+
+const DATE: &[FormatItem<'static>] = format_description!("[year]-[month]-[day]");
+
+/// A type which is represented as an RFC 3339 string.
+pub trait Rfc3339: Sized {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer;
+
+    fn deserialize<'de, D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>;
+}
+
+impl Rfc3339 for OffsetDateTime {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let string = self.format(&DateTime).map_err(ser::Error::custom)?;
+        serializer.serialize_str(&string)
+    }
+
+    fn deserialize<'de, D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let string = String::deserialize(deserializer)?;
+        OffsetDateTime::parse(&string, &DateTime).map_err(de::Error::custom)
+    }
+}
+
+impl Rfc3339 for Date {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let string = self.format(DATE).map_err(ser::Error::custom)?;
+        serializer.serialize_str(&string)
+    }
+
+    fn deserialize<'de, D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let string = String::deserialize(deserializer)?;
+        Date::parse(&string, DATE).map_err(de::Error::custom)
+    }
+}
+
+impl<T> Rfc3339 for Option<T>
+where
+    T: Rfc3339,
+{
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match self {
+            Some(value) => value.serialize(serializer),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    fn deserialize<'de, D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct Value<T>(T);
+
+        impl<'de, T> Deserialize<'de> for Value<T>
+        where
+            T: Rfc3339,
+        {
+            fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+            where
+                D: Deserializer<'de>,
+            {
+                T::deserialize(deserializer).map(Value)
+            }
+        }
+
+        Ok(Option::<Value<T>>::deserialize(deserializer)?.map(|value| value.0))
+    }
+}
+
+pub fn serialize<T, S>(value: &T, serializer: S) -> Result<S::Ok, S::Error>
+where
+    T: Rfc3339,
+    S: Serializer,
+{
+    value.serialize(serializer)
+}
+
+pub fn deserialize<'de, T, D>(deserializer: D) -> Result<T, D::Error>
+where
+    T: Rfc3339,
+    D: Deserializer<'de>,
+{
+    T::deserialize(deserializer)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::{Deserialize, Serialize};
+    use time::macros::{date, datetime};
+
+    use super::*;
+
+    #[derive(Debug, PartialEq, Deserialize, Serialize)]
+    struct Event {
+        #[serde(with = "crate::rfc3339")]
+        at: OffsetDateTime,
+        #[serde(default, with = "crate::rfc3339")]
+        on: Option<Date>,
+    }
+
+    #[test]
+    fn round_trip() {
+        let event: Event =
+            serde_json::from_str(r#"{ "at": "2020-01-02T03:04:05+01:00" }"#).unwrap();
+        assert_eq!(
+            event,
+            Event {
+                at: datetime!(2020-01-02 03:04:05 +01:00),
+                on: None,
+            }
+        );
+
+        let event = Event {
+            on: Some(date!(2020 - 01 - 02)),
+            ..event
+        };
+        let json = serde_json::to_string(&event).unwrap();
+        assert_eq!(
+            json,
+            r#"{"at":"2020-01-02T03:04:05+01:00","on":"2020-01-02"}"#
+        );
+        assert_eq!(serde_json::from_str::<Event>(&json).unwrap(), event);
+        assert!(serde_json::from_str::<Event>(r#"{ "at": "2020-01-02" }"#).is_err());
+    }
+}
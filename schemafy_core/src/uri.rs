@@ -0,0 +1,153 @@
+//! Resolution of the URI references used by `$ref`, `$id` and `id`.
+
+/// Splits a reference into the document and the fragment (without `#`) it points at.
+pub fn split_ref(ref_: &str) -> (&str, &str) {
+    match ref_.find('#') {
+        Some(i) => (&ref_[..i], &ref_[i + 1..]),
+        None => (ref_, ""),
+    }
+}
+
+/// Resolves the (possibly relative) `reference` against the location of the document `base`.
+pub fn resolve_uri(base: &str, reference: &str) -> String {
+    if reference.is_empty() {
+        return base.to_string();
+    }
+    let (prefix, path) = if has_scheme(reference) {
+        split_authority(reference)
+    } else if reference.starts_with('/') {
+        (split_authority(base).0, reference)
+    } else if is_drive_path(reference) {
+        ("", reference)
+    } else {
+        let (prefix, base_path) = split_authority(base);
+        let directory = base_path.rfind('/').map_or("", |i| &base_path[..=i]);
+        return format!(
+            "{}{}",
+            prefix,
+            remove_dot_segments(&format!("{}{}", directory, reference))
+        );
+    };
+    format!("{}{}", prefix, remove_dot_segments(path))
+}
+
+/// Returns true if `uri` starts with a scheme such as `https:`.
+pub fn has_scheme(uri: &str) -> bool {
+    match uri.find(':') {
+        // Single letters are drive letters, not schemes
+        Some(i) if i > 1 => uri[..i]
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '+' || c == '-' || c == '.'),
+        _ => false,
+    }
+}
+
+/// Returns true for absolute Windows paths such as `C:/schemas/root.json`.
+fn is_drive_path(path: &str) -> bool {
+    let bytes = path.as_bytes();
+    bytes.len() > 2 && bytes[0].is_ascii_alphabetic() && bytes[1] == b':' && bytes[2] == b'/'
+}
+
+/// Splits `scheme://authority` from the path of `uri`.
+fn split_authority(uri: &str) -> (&str, &str) {
+    match uri.find("://") {
+        Some(i) => {
+            let path_start = uri[i + 3..].find('/').map_or(uri.len(), |j| i + 3 + j);
+            (&uri[..path_start], &uri[path_start..])
+        }
+        None if has_scheme(uri) => {
+            let i = uri.find(':').unwrap();
+            (&uri[..=i], &uri[i + 1..])
+        }
+        None => ("", uri),
+    }
+}
+
+fn remove_dot_segments(path: &str) -> String {
+    let mut segments: Vec<&str> = Vec::new();
+    for segment in path.split('/') {
+        match segment {
+            "." => (),
+            ".." if matches!(segments.last(), Some(s) if !s.is_empty()) => {
+                segments.pop();
+            }
+            ".." => (),
+            _ => segments.push(segment),
+        }
+    }
+    segments.join("/")
+}
+
+/// Decodes the percent-encoded bytes (such as `%25`) of a URI fragment.
+pub fn percent_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = s.get(i + 1..i + 3);
+        match hex.and_then(|hex| u8::from_str_radix(hex, 16).ok()) {
+            Some(byte) if bytes[i] == b'%' => {
+                decoded.push(byte);
+                i += 3;
+            }
+            _ => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resolve_relative_paths() {
+        assert_eq!(
+            resolve_uri("/a/b/root.json", "common.json"),
+            "/a/b/common.json"
+        );
+        assert_eq!(
+            resolve_uri("/a/b/root.json", "./c/common.json"),
+            "/a/b/c/common.json"
+        );
+        assert_eq!(
+            resolve_uri("/a/b/root.json", "../common.json"),
+            "/a/common.json"
+        );
+        assert_eq!(
+            resolve_uri("/a/b/root.json", "/common.json"),
+            "/common.json"
+        );
+        assert_eq!(resolve_uri("/a/b/root.json", ""), "/a/b/root.json");
+        assert_eq!(resolve_uri("root.json", "common.json"), "common.json");
+    }
+
+    #[test]
+    fn resolve_uris() {
+        assert_eq!(
+            resolve_uri("http://example.com/schemas/root.json", "common.json"),
+            "http://example.com/schemas/common.json"
+        );
+        assert_eq!(
+            resolve_uri("http://example.com/schemas/root.json", "/common.json"),
+            "http://example.com/common.json"
+        );
+        assert_eq!(
+            resolve_uri("/a/root.json", "http://example.com/x.json"),
+            "http://example.com/x.json"
+        );
+    }
+
+    #[test]
+    fn split_refs() {
+        assert_eq!(split_ref("#/definitions/a"), ("", "/definitions/a"));
+        assert_eq!(
+            split_ref("a.json#/definitions/a"),
+            ("a.json", "/definitions/a")
+        );
+        assert_eq!(split_ref("a.json"), ("a.json", ""));
+    }
+}
@@ -0,0 +1,732 @@
+//! Checks the constraint keywords (`minimum`, `maxLength`, `pattern`, ...) which the structure
+//! of the generated types can not express.
+//!
+//! The types are only given a [`Validate`] implementation when they are generated with
+//! validation enabled (`validate: true` in the `schemafy!` macro).
+
+use std::{
+    cell::RefCell,
+    collections::{BTreeMap, HashMap},
+    fmt,
+    net::{Ipv4Addr, Ipv6Addr},
+};
+
+use regex::Regex;
+
+/// A value which violates a constraint of its schema.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ValidationError {
+    pointer: String,
+    message: String,
+}
+
+impl ValidationError {
+    pub fn new(pointer: impl Into<String>, message: impl Into<String>) -> ValidationError {
+        ValidationError {
+            pointer: pointer.into(),
+            message: message.into(),
+        }
+    }
+
+    /// The JSON pointer to the invalid value, relative to the validated value.
+    pub fn pointer(&self) -> &str {
+        &self.pointer
+    }
+
+    /// The violated constraint.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.pointer, self.message)
+    }
+}
+
+impl std::error::Error for ValidationError {}
+
+/// Every constraint violation found in a value.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ValidationErrors(Vec<ValidationError>);
+
+impl ValidationErrors {
+    pub fn new() -> ValidationErrors {
+        ValidationErrors::default()
+    }
+
+    pub fn push(&mut self, error: ValidationError) {
+        self.0.push(error);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &ValidationError> {
+        self.0.iter()
+    }
+}
+
+impl IntoIterator for ValidationErrors {
+    type Item = ValidationError;
+    type IntoIter = std::vec::IntoIter<ValidationError>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+impl fmt::Display for ValidationErrors {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, error) in self.0.iter().enumerate() {
+            if i != 0 {
+                writeln!(f)?;
+            }
+            write!(f, "{}", error)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ValidationErrors {}
+
+/// Types which can check the constraints of the schema they were generated from.
+pub trait Validate {
+    /// Adds every constraint violation of `self`, located at `pointer`, to `errors`.
+    fn validate_at(&self, pointer: &str, errors: &mut ValidationErrors);
+
+    /// Checks the constraints of `self` and everything it contains.
+    fn validate(&self) -> Result<(), ValidationErrors> {
+        let mut errors = ValidationErrors::new();
+        self.validate_at("", &mut errors);
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+macro_rules! impl_validate_noop {
+    ($($typ: ty),*) => {
+        $(
+            impl Validate for $typ {
+                fn validate_at(&self, _: &str, _: &mut ValidationErrors) {}
+            }
+        )*
+    };
+}
+
+impl_validate_noop!(
+    bool,
+    i8,
+    i16,
+    i32,
+    i64,
+    i128,
+    u8,
+    u16,
+    u32,
+    u64,
+    u128,
+    f64,
+    String,
+    serde_json::Value,
+    Ipv4Addr,
+    Ipv6Addr,
+    crate::number::Number
+);
+
+#[cfg(feature = "decimal")]
+impl_validate_noop!(rust_decimal::Decimal);
+
+#[cfg(feature = "chrono")]
+impl_validate_noop!(chrono::DateTime<chrono::FixedOffset>, chrono::NaiveDate);
+
+#[cfg(feature = "time")]
+impl_validate_noop!(time::OffsetDateTime, time::Date);
+
+#[cfg(feature = "uuid")]
+impl_validate_noop!(uuid::Uuid);
+
+#[cfg(feature = "url")]
+impl_validate_noop!(url::Url);
+
+impl<T> Validate for Option<T>
+where
+    T: Validate,
+{
+    fn validate_at(&self, pointer: &str, errors: &mut ValidationErrors) {
+        if let Some(value) = self {
+            value.validate_at(pointer, errors);
+        }
+    }
+}
+
+impl<T> Validate for Box<T>
+where
+    T: Validate + ?Sized,
+{
+    fn validate_at(&self, pointer: &str, errors: &mut ValidationErrors) {
+        (**self).validate_at(pointer, errors);
+    }
+}
+
+impl<T> Validate for Vec<T>
+where
+    T: Validate,
+{
+    fn validate_at(&self, pointer: &str, errors: &mut ValidationErrors) {
+        for (i, item) in self.iter().enumerate() {
+            item.validate_at(&child(pointer, &i.to_string()), errors);
+        }
+    }
+}
+
+impl<T, const N: usize> Validate for [T; N]
+where
+    T: Validate,
+{
+    fn validate_at(&self, pointer: &str, errors: &mut ValidationErrors) {
+        for (i, item) in self.iter().enumerate() {
+            item.validate_at(&child(pointer, &i.to_string()), errors);
+        }
+    }
+}
+
+impl<K, T> Validate for BTreeMap<K, T>
+where
+    K: serde::Serialize,
+    T: Validate,
+{
+    fn validate_at(&self, pointer: &str, errors: &mut ValidationErrors) {
+        for (key, value) in self {
+            // Keys are strings or enums which serialize as the string they were named by
+            let token = match serde_json::to_value(key) {
+                Ok(serde_json::Value::String(token)) => token,
+                Ok(token) => token.to_string(),
+                Err(err) => err.to_string(),
+            };
+            value.validate_at(&child(pointer, &token), errors);
+        }
+    }
+}
+
+/// Returns the pointer to the value at `token` within the value at `pointer`.
+pub fn child(pointer: &str, token: &str) -> String {
+    format!(
+        "{}/{}",
+        pointer,
+        token.replace('~', "~0").replace('/', "~1")
+    )
+}
+
+/// The parts of a value which constraints apply to. Values which are absent (`None`) satisfy
+/// every constraint.
+pub trait Constrained {
+    fn number(&self) -> Option<f64> {
+        None
+    }
+
+    fn string(&self) -> Option<&str> {
+        None
+    }
+
+    /// Calls `f` with every item of an array.
+    fn items(&self, _f: &mut dyn FnMut(usize, &dyn Constrained)) {}
+
+    /// The number of items of an array.
+    fn count(&self) -> Option<usize> {
+        None
+    }
+
+    /// Whether the items of an array are all distinct.
+    fn unique(&self) -> Option<bool> {
+        None
+    }
+}
+
+impl Constrained for bool {}
+
+macro_rules! impl_constrained_integer {
+    ($($typ: ty),*) => {
+        $(
+            impl Constrained for $typ {
+                fn number(&self) -> Option<f64> {
+                    Some(*self as f64)
+                }
+            }
+        )*
+    };
+}
+
+impl_constrained_integer!(i8, i16, i32, i64, i128, u8, u16, u32, u64, u128);
+
+impl Constrained for f64 {
+    fn number(&self) -> Option<f64> {
+        Some(*self)
+    }
+}
+
+impl Constrained for crate::number::Number {
+    fn number(&self) -> Option<f64> {
+        self.as_f64()
+    }
+}
+
+#[cfg(feature = "decimal")]
+impl Constrained for rust_decimal::Decimal {
+    fn number(&self) -> Option<f64> {
+        use rust_decimal::prelude::ToPrimitive;
+        self.to_f64()
+    }
+}
+
+impl Constrained for String {
+    fn string(&self) -> Option<&str> {
+        Some(self)
+    }
+}
+
+impl<K, T> Constrained for BTreeMap<K, T> {}
+
+impl Constrained for Ipv4Addr {}
+
+impl Constrained for Ipv6Addr {}
+
+#[cfg(feature = "chrono")]
+impl Constrained for chrono::DateTime<chrono::FixedOffset> {}
+
+#[cfg(feature = "chrono")]
+impl Constrained for chrono::NaiveDate {}
+
+#[cfg(feature = "time")]
+impl Constrained for time::OffsetDateTime {}
+
+#[cfg(feature = "time")]
+impl Constrained for time::Date {}
+
+#[cfg(feature = "uuid")]
+impl Constrained for uuid::Uuid {}
+
+#[cfg(feature = "url")]
+impl Constrained for url::Url {
+    fn string(&self) -> Option<&str> {
+        Some(self.as_str())
+    }
+}
+
+impl Constrained for serde_json::Value {
+    fn number(&self) -> Option<f64> {
+        self.as_f64()
+    }
+
+    fn string(&self) -> Option<&str> {
+        self.as_str()
+    }
+
+    fn items(&self, f: &mut dyn FnMut(usize, &dyn Constrained)) {
+        if let Some(items) = self.as_array() {
+            items.items(f);
+        }
+    }
+
+    fn count(&self) -> Option<usize> {
+        self.as_array().map(Vec::len)
+    }
+
+    fn unique(&self) -> Option<bool> {
+        self.as_array().and_then(Constrained::unique)
+    }
+}
+
+impl<T> Constrained for Option<T>
+where
+    T: Constrained,
+{
+    fn number(&self) -> Option<f64> {
+        self.as_ref()?.number()
+    }
+
+    fn string(&self) -> Option<&str> {
+        self.as_ref()?.string()
+    }
+
+    fn items(&self, f: &mut dyn FnMut(usize, &dyn Constrained)) {
+        if let Some(value) = self {
+            value.items(f);
+        }
+    }
+
+    fn count(&self) -> Option<usize> {
+        self.as_ref()?.count()
+    }
+
+    fn unique(&self) -> Option<bool> {
+        self.as_ref()?.unique()
+    }
+}
+
+impl<T> Constrained for Box<T>
+where
+    T: Constrained + ?Sized,
+{
+    fn number(&self) -> Option<f64> {
+        (**self).number()
+    }
+
+    fn string(&self) -> Option<&str> {
+        (**self).string()
+    }
+
+    fn items(&self, f: &mut dyn FnMut(usize, &dyn Constrained)) {
+        (**self).items(f);
+    }
+
+    fn count(&self) -> Option<usize> {
+        (**self).count()
+    }
+
+    fn unique(&self) -> Option<bool> {
+        (**self).unique()
+    }
+}
+
+impl<T> Constrained for Vec<T>
+where
+    T: Constrained + PartialEq,
+{
+    fn items(&self, f: &mut dyn FnMut(usize, &dyn Constrained)) {
+        for (i, item) in self.iter().enumerate() {
+            f(i, item);
+        }
+    }
+
+    fn count(&self) -> Option<usize> {
+        Some(Vec::len(self))
+    }
+
+    fn unique(&self) -> Option<bool> {
+        Some(is_unique(self))
+    }
+}
+
+impl<T, const N: usize> Constrained for [T; N]
+where
+    T: Constrained + PartialEq,
+{
+    fn items(&self, f: &mut dyn FnMut(usize, &dyn Constrained)) {
+        for (i, item) in self.iter().enumerate() {
+            f(i, item);
+        }
+    }
+
+    fn count(&self) -> Option<usize> {
+        Some(N)
+    }
+
+    fn unique(&self) -> Option<bool> {
+        Some(is_unique(self))
+    }
+}
+
+fn is_unique<T>(items: &[T]) -> bool
+where
+    T: PartialEq,
+{
+    items
+        .iter()
+        .enumerate()
+        .all(|(i, item)| !items[..i].contains(item))
+}
+
+// Tuples are the arrays whose items are given by position. Their items may differ in type so
+// `uniqueItems` is not checked.
+macro_rules! impl_tuple {
+    ($len: expr => $($index: tt $typ: ident),+) => {
+        impl<$($typ),+> Validate for ($($typ,)+)
+        where
+            $($typ: Validate),+
+        {
+            fn validate_at(&self, pointer: &str, errors: &mut ValidationErrors) {
+                $(self.$index.validate_at(&child(pointer, stringify!($index)), errors);)+
+            }
+        }
+
+        impl<$($typ),+> Constrained for ($($typ,)+)
+        where
+            $($typ: Constrained),+
+        {
+            fn items(&self, f: &mut dyn FnMut(usize, &dyn Constrained)) {
+                $(f($index, &self.$index);)+
+            }
+
+            fn count(&self) -> Option<usize> {
+                Some($len)
+            }
+        }
+    };
+}
+
+impl_tuple!(1 => 0 A);
+impl_tuple!(2 => 0 A, 1 B);
+impl_tuple!(3 => 0 A, 1 B, 2 C);
+impl_tuple!(4 => 0 A, 1 B, 2 C, 3 D);
+impl_tuple!(5 => 0 A, 1 B, 2 C, 3 D, 4 E);
+impl_tuple!(6 => 0 A, 1 B, 2 C, 3 D, 4 E, 5 F);
+impl_tuple!(7 => 0 A, 1 B, 2 C, 3 D, 4 E, 5 F, 6 G);
+impl_tuple!(8 => 0 A, 1 B, 2 C, 3 D, 4 E, 5 F, 6 G, 7 H);
+impl_tuple!(9 => 0 A, 1 B, 2 C, 3 D, 4 E, 5 F, 6 G, 7 H, 8 I);
+impl_tuple!(10 => 0 A, 1 B, 2 C, 3 D, 4 E, 5 F, 6 G, 7 H, 8 I, 9 J);
+impl_tuple!(11 => 0 A, 1 B, 2 C, 3 D, 4 E, 5 F, 6 G, 7 H, 8 I, 9 J, 10 K);
+impl_tuple!(12 => 0 A, 1 B, 2 C, 3 D, 4 E, 5 F, 6 G, 7 H, 8 I, 9 J, 10 K, 11 L);
+impl_tuple!(13 => 0 A, 1 B, 2 C, 3 D, 4 E, 5 F, 6 G, 7 H, 8 I, 9 J, 10 K, 11 L, 12 M);
+impl_tuple!(14 => 0 A, 1 B, 2 C, 3 D, 4 E, 5 F, 6 G, 7 H, 8 I, 9 J, 10 K, 11 L, 12 M, 13 N);
+impl_tuple!(15 => 0 A, 1 B, 2 C, 3 D, 4 E, 5 F, 6 G, 7 H, 8 I, 9 J, 10 K, 11 L, 12 M, 13 N, 14 O);
+impl_tuple!(16 => 0 A, 1 B, 2 C, 3 D, 4 E, 5 F, 6 G, 7 H, 8 I, 9 J, 10 K, 11 L, 12 M, 13 N, 14 O, 15 P);
+
+/// The constraint keywords of a schema. `exclusive_minimum` and `exclusive_maximum` are the
+/// limits of draft 6 and later.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Constraints {
+    pub minimum: Option<f64>,
+    pub maximum: Option<f64>,
+    pub exclusive_minimum: Option<f64>,
+    pub exclusive_maximum: Option<f64>,
+    pub multiple_of: Option<f64>,
+    pub min_length: Option<u64>,
+    pub max_length: Option<u64>,
+    /// Must be supported by the `regex` crate, `validate` panics otherwise.
+    pub pattern: Option<&'static str>,
+    pub min_items: Option<u64>,
+    pub max_items: Option<u64>,
+    pub unique_items: bool,
+    /// The constraints of the items of an array.
+    pub items: Option<Box<Constraints>>,
+    /// Constraints which must hold as well, such as a second `pattern` from an `allOf`.
+    pub all_of: Vec<Constraints>,
+}
+
+impl Constraints {
+    /// Adds every constraint which `value`, located at `pointer`, violates to `errors`.
+    pub fn check(&self, value: &dyn Constrained, pointer: &str, errors: &mut ValidationErrors) {
+        let mut error = |message: String| errors.push(ValidationError::new(pointer, message));
+        if let Some(number) = value.number() {
+            match self.minimum {
+                Some(minimum) if number < minimum => error(format!(
+                    "{} is less than the minimum of {}",
+                    number, minimum
+                )),
+                _ => (),
+            }
+            match self.maximum {
+                Some(maximum) if number > maximum => error(format!(
+                    "{} is greater than the maximum of {}",
+                    number, maximum
+                )),
+                _ => (),
+            }
+            match self.exclusive_minimum {
+                Some(minimum) if number <= minimum => error(format!(
+                    "{} is less than or equal to the exclusive minimum of {}",
+                    number, minimum
+                )),
+                _ => (),
+            }
+            match self.exclusive_maximum {
+                Some(maximum) if number >= maximum => error(format!(
+                    "{} is greater than or equal to the exclusive maximum of {}",
+                    number, maximum
+                )),
+                _ => (),
+            }
+            match self.multiple_of {
+                Some(multiple_of) if !is_multiple_of(number, multiple_of) => {
+                    error(format!("{} is not a multiple of {}", number, multiple_of))
+                }
+                _ => (),
+            }
+        }
+        if let Some(string) = value.string() {
+            let length = string.chars().count() as u64;
+            match self.min_length {
+                Some(min_length) if length < min_length => error(format!(
+                    "{:?} is shorter than {} characters",
+                    string, min_length
+                )),
+                _ => (),
+            }
+            match self.max_length {
+                Some(max_length) if length > max_length => error(format!(
+                    "{:?} is longer than {} characters",
+                    string, max_length
+                )),
+                _ => (),
+            }
+            match self.pattern {
+                Some(pattern) if !is_match(pattern, string) => {
+                    error(format!("{:?} does not match {:?}", string, pattern))
+                }
+                _ => (),
+            }
+        }
+        if let Some(len) = value.count() {
+            let len = len as u64;
+            match self.min_items {
+                Some(min_items) if len < min_items => error(format!(
+                    "Expected at least {} items, got {}",
+                    min_items, len
+                )),
+                _ => (),
+            }
+            match self.max_items {
+                Some(max_items) if len > max_items => {
+                    error(format!("Expected at most {} items, got {}", max_items, len))
+                }
+                _ => (),
+            }
+            if self.unique_items && value.unique() == Some(false) {
+                error("Expected unique items".to_string());
+            }
+        }
+        if let Some(ref items) = self.items {
+            value.items(&mut |i, item| items.check(item, &child(pointer, &i.to_string()), errors));
+        }
+        for constraints in &self.all_of {
+            constraints.check(value, pointer, errors);
+        }
+    }
+}
+
+pub(crate) fn is_multiple_of(number: f64, multiple_of: f64) -> bool {
+    let quotient = number / multiple_of;
+    if !quotient.is_finite() {
+        return false;
+    }
+    // Allows for the rounding error of the division, which grows with the quotient
+    (quotient - quotient.round()).abs() <= 4.0 * f64::EPSILON * quotient.abs().max(1.0)
+}
+
+fn is_match(pattern: &'static str, string: &str) -> bool {
+    thread_local! {
+        static REGEXES: RefCell<HashMap<&'static str, Regex>> = RefCell::new(HashMap::new());
+    }
+    REGEXES.with(|regexes| {
+        regexes
+            .borrow_mut()
+            .entry(pattern)
+            .or_insert_with(|| {
+                Regex::new(pattern).unwrap_or_else(|err| panic!("Invalid pattern: {}", err))
+            })
+            .is_match(string)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check(constraints: &Constraints, value: &dyn Constrained) -> Vec<String> {
+        let mut errors = ValidationErrors::new();
+        constraints.check(value, "/a", &mut errors);
+        errors.iter().map(|error| error.to_string()).collect()
+    }
+
+    #[test]
+    fn numbers() {
+        let constraints = Constraints {
+            minimum: Some(1.0),
+            exclusive_maximum: Some(10.0),
+            multiple_of: Some(0.5),
+            ..Constraints::default()
+        };
+        assert!(check(&constraints, &2.5).is_empty());
+        assert!(check(&constraints, &None::<f64>).is_empty());
+        assert_eq!(
+            check(&constraints, &10.25),
+            [
+                "/a: 10.25 is greater than or equal to the exclusive maximum of 10",
+                "/a: 10.25 is not a multiple of 0.5"
+            ]
+        );
+        assert_eq!(
+            check(&constraints, &Some(0i64)),
+            ["/a: 0 is less than the minimum of 1"]
+        );
+    }
+
+    #[test]
+    fn multiples() {
+        assert!(is_multiple_of(0.0075, 0.0001));
+        assert!(!is_multiple_of(0.00751, 0.0001));
+        assert!(is_multiple_of(12391239123.0, 1e-8));
+        assert!(!is_multiple_of(1e12 + 0.5, 1.0));
+        assert!(!is_multiple_of(1e308, 1e-10));
+    }
+
+    #[test]
+    fn strings() {
+        let constraints = Constraints {
+            max_length: Some(2),
+            pattern: Some("^a"),
+            ..Constraints::default()
+        };
+        assert!(check(&constraints, &"aé".to_string()).is_empty());
+        assert_eq!(
+            check(&constraints, &"bcd".to_string()),
+            [
+                r#"/a: "bcd" is longer than 2 characters"#,
+                r#"/a: "bcd" does not match "^a""#
+            ]
+        );
+
+        let constraints = Constraints {
+            all_of: vec![Constraints {
+                pattern: Some("b$"),
+                ..Constraints::default()
+            }],
+            ..constraints
+        };
+        assert!(check(&constraints, &"ab".to_string()).is_empty());
+        assert_eq!(
+            check(&constraints, &"a".to_string()),
+            [r#"/a: "a" does not match "b$""#]
+        );
+    }
+
+    #[test]
+    fn arrays() {
+        let constraints = Constraints {
+            min_items: Some(3),
+            unique_items: true,
+            items: Some(Box::new(Constraints {
+                minimum: Some(0.0),
+                ..Constraints::default()
+            })),
+            ..Constraints::default()
+        };
+        assert_eq!(
+            check(&constraints, &vec![1i64, -1, 1]),
+            [
+                "/a: Expected unique items",
+                "/a/1: -1 is less than the minimum of 0"
+            ]
+        );
+        assert_eq!(
+            check(&constraints, &serde_json::json!([1])),
+            ["/a: Expected at least 3 items, got 1"]
+        );
+    }
+
+    #[test]
+    fn pointers() {
+        assert_eq!(child("", "a/b~c"), "/a~1b~0c");
+        assert_eq!(child("/a", "0"), "/a/0");
+    }
+}
@@ -0,0 +1,1182 @@
+//! Validates JSON values against a JSON schema at runtime, for instance to check incoming data
+//! before it is deserialized into the generated types.
+//!
+//! Drafts 4, 6, 7, 2019-09 and 2020-12 are supported, except for `format` (which is treated as
+//! an annotation), `unevaluatedItems`, `unevaluatedProperties` and the dynamic scoping of
+//! `$recursiveRef` and `$dynamicRef` (which are resolved like `$ref`). Patterns which the `regex`
+//! crate does not support are reported as a `SchemaError`.
+
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+};
+
+use regex::Regex;
+use serde_json::{Map, Value};
+
+use crate::{
+    uri::{percent_decode, resolve_uri, split_ref},
+    validate::{child, is_multiple_of, ValidationError, ValidationErrors},
+};
+
+/// A schema which could not be compiled into a [`Validator`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct SchemaError {
+    uri: String,
+    message: String,
+}
+
+impl SchemaError {
+    fn new(uri: impl Into<String>, message: impl Into<String>) -> SchemaError {
+        SchemaError {
+            uri: uri.into(),
+            message: message.into(),
+        }
+    }
+
+    /// The URI of the offending schema.
+    pub fn uri(&self) -> &str {
+        &self.uri
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+impl fmt::Display for SchemaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} (at `{}`)", self.message, self.uri)
+    }
+}
+
+impl std::error::Error for SchemaError {}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum Draft {
+    Draft4,
+    Draft6,
+    Draft7,
+    Draft201909,
+    Draft202012,
+}
+
+impl Draft {
+    /// Determines the draft from `$schema`, assuming draft 4 like the code generator does.
+    fn of(schema: &Value) -> Draft {
+        let uri = match schema.get("$schema").and_then(Value::as_str) {
+            Some(uri) => uri,
+            None => return Draft::Draft4,
+        };
+        let uri = uri.trim_end_matches('#').trim_end_matches('/');
+        if uri.ends_with("draft-06/schema") {
+            Draft::Draft6
+        } else if uri.ends_with("draft-07/schema") {
+            Draft::Draft7
+        } else if uri.ends_with("draft/2019-09/schema") {
+            Draft::Draft201909
+        } else if uri.ends_with("draft/2020-12/schema") {
+            Draft::Draft202012
+        } else {
+            Draft::Draft4
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Type {
+    Array,
+    Boolean,
+    Integer,
+    Null,
+    Number,
+    Object,
+    String,
+}
+
+impl Type {
+    fn from_name(name: &str) -> Option<Type> {
+        Some(match name {
+            "array" => Type::Array,
+            "boolean" => Type::Boolean,
+            "integer" => Type::Integer,
+            "null" => Type::Null,
+            "number" => Type::Number,
+            "object" => Type::Object,
+            "string" => Type::String,
+            _ => return None,
+        })
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Type::Array => "array",
+            Type::Boolean => "boolean",
+            Type::Integer => "integer",
+            Type::Null => "null",
+            Type::Number => "number",
+            Type::Object => "object",
+            Type::String => "string",
+        }
+    }
+}
+
+/// A compiled keyword. Subschemas are referred to by their index in `Validator::nodes`.
+#[derive(Clone, Debug)]
+enum Keyword {
+    False,
+    Ref(String),
+    Link(usize),
+    Type(Vec<Type>),
+    Enum(Vec<Value>),
+    Const(Value),
+    MultipleOf(f64),
+    Maximum(f64),
+    ExclusiveMaximum(f64),
+    Minimum(f64),
+    ExclusiveMinimum(f64),
+    MaxLength(u64),
+    MinLength(u64),
+    Pattern(Regex),
+    Items {
+        prefix: Vec<usize>,
+        rest: Option<usize>,
+    },
+    MaxItems(u64),
+    MinItems(u64),
+    UniqueItems,
+    Contains {
+        schema: usize,
+        min: u64,
+        max: Option<u64>,
+    },
+    MaxProperties(u64),
+    MinProperties(u64),
+    Required(Vec<String>),
+    Properties {
+        properties: Vec<(String, usize)>,
+        patterns: Vec<(Regex, usize)>,
+        additional: Option<usize>,
+    },
+    DependentRequired(Vec<(String, Vec<String>)>),
+    DependentSchemas(Vec<(String, usize)>),
+    PropertyNames(usize),
+    AllOf(Vec<usize>),
+    AnyOf(Vec<usize>),
+    OneOf(Vec<usize>),
+    Not(usize),
+    If {
+        if_: usize,
+        then: Option<usize>,
+        else_: Option<usize>,
+    },
+}
+
+/// A schema compiled for validating values.
+#[derive(Clone, Debug)]
+pub struct Validator {
+    nodes: Vec<Vec<Keyword>>,
+    root: usize,
+}
+
+impl Validator {
+    /// Compiles `schema`, whose `$ref`s may only point into `schema` itself.
+    pub fn new(schema: &Value) -> Result<Validator, SchemaError> {
+        Validator::with_documents("", schema, None)
+    }
+
+    /// Compiles `schema`, located at `uri`, whose `$ref`s may also point into the `documents`
+    /// (keyed by their URIs).
+    pub fn with_documents<'a>(
+        uri: &str,
+        schema: &Value,
+        documents: impl IntoIterator<Item = (&'a str, &'a Value)>,
+    ) -> Result<Validator, SchemaError> {
+        let mut compiler = Compiler {
+            draft: Draft::of(schema),
+            nodes: Vec::new(),
+            uris: HashMap::new(),
+            documents: documents
+                .into_iter()
+                .map(|(uri, document)| (uri.to_string(), document.clone()))
+                .collect(),
+            compiled: HashSet::new(),
+            unlinked: Vec::new(),
+        };
+        compiler.documents.insert(uri.to_string(), schema.clone());
+        compiler.compiled.insert(uri.to_string());
+        let root = compiler.compile(schema, uri, "")?;
+        compiler.link()?;
+        Ok(Validator {
+            nodes: compiler.nodes,
+            root,
+        })
+    }
+
+    /// Checks `value` against the schema, returning every violation.
+    pub fn validate(&self, value: &Value) -> Result<(), ValidationErrors> {
+        let mut errors = ValidationErrors::new();
+        self.validate_node(self.root, value, "", &mut errors);
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    pub fn is_valid(&self, value: &Value) -> bool {
+        self.validate(value).is_ok()
+    }
+
+    fn is_valid_node(&self, node: usize, value: &Value) -> bool {
+        let mut errors = ValidationErrors::new();
+        self.validate_node(node, value, "", &mut errors);
+        errors.is_empty()
+    }
+
+    fn validate_node(
+        &self,
+        node: usize,
+        value: &Value,
+        pointer: &str,
+        errors: &mut ValidationErrors,
+    ) {
+        for keyword in &self.nodes[node] {
+            self.validate_keyword(keyword, value, pointer, errors);
+        }
+    }
+
+    fn validate_keyword(
+        &self,
+        keyword: &Keyword,
+        value: &Value,
+        pointer: &str,
+        errors: &mut ValidationErrors,
+    ) {
+        let mut error = |message: String| errors.push(ValidationError::new(pointer, message));
+        match *keyword {
+            Keyword::False => error("No value is allowed".to_string()),
+            Keyword::Ref(_) => unreachable!("References are linked when compiling"),
+            Keyword::Link(node) => self.validate_node(node, value, pointer, errors),
+            Keyword::Type(ref types) => {
+                if !types.iter().any(|&typ| is_type(typ, value)) {
+                    let names = types.iter().map(|typ| typ.name()).collect::<Vec<_>>();
+                    error(format!("Expected {}, got {}", names.join(" or "), value));
+                }
+            }
+            Keyword::Enum(ref values) => {
+                if !values.iter().any(|v| equal(v, value)) {
+                    error(format!("{} is not one of the allowed values", value));
+                }
+            }
+            Keyword::Const(ref constant) => {
+                if !equal(constant, value) {
+                    error(format!("Expected {}, got {}", constant, value));
+                }
+            }
+            Keyword::MultipleOf(multiple_of) => match value.as_f64() {
+                Some(number) if !is_multiple_of(number, multiple_of) => {
+                    error(format!("{} is not a multiple of {}", number, multiple_of))
+                }
+                _ => (),
+            },
+            Keyword::Maximum(maximum) => match value.as_f64() {
+                Some(number) if number > maximum => error(format!(
+                    "{} is greater than the maximum of {}",
+                    number, maximum
+                )),
+                _ => (),
+            },
+            Keyword::ExclusiveMaximum(maximum) => match value.as_f64() {
+                Some(number) if number >= maximum => error(format!(
+                    "{} is greater than or equal to the exclusive maximum of {}",
+                    number, maximum
+                )),
+                _ => (),
+            },
+            Keyword::Minimum(minimum) => match value.as_f64() {
+                Some(number) if number < minimum => error(format!(
+                    "{} is less than the minimum of {}",
+                    number, minimum
+                )),
+                _ => (),
+            },
+            Keyword::ExclusiveMinimum(minimum) => match value.as_f64() {
+                Some(number) if number <= minimum => error(format!(
+                    "{} is less than or equal to the exclusive minimum of {}",
+                    number, minimum
+                )),
+                _ => (),
+            },
+            Keyword::MaxLength(max_length) => match value.as_str() {
+                Some(string) if string.chars().count() as u64 > max_length => error(format!(
+                    "{:?} is longer than {} characters",
+                    string, max_length
+                )),
+                _ => (),
+            },
+            Keyword::MinLength(min_length) => match value.as_str() {
+                Some(string) if (string.chars().count() as u64) < min_length => error(format!(
+                    "{:?} is shorter than {} characters",
+                    string, min_length
+                )),
+                _ => (),
+            },
+            Keyword::Pattern(ref regex) => match value.as_str() {
+                Some(string) if !regex.is_match(string) => {
+                    error(format!("{:?} does not match {:?}", string, regex.as_str()))
+                }
+                _ => (),
+            },
+            Keyword::Items { ref prefix, rest } => {
+                if let Some(items) = value.as_array() {
+                    for (i, item) in items.iter().enumerate() {
+                        let node = match prefix.get(i) {
+                            Some(&node) => node,
+                            None => match rest {
+                                Some(node) => node,
+                                None => break,
+                            },
+                        };
+                        self.validate_node(node, item, &child(pointer, &i.to_string()), errors);
+                    }
+                }
+            }
+            Keyword::MaxItems(max_items) => match value.as_array() {
+                Some(items) if items.len() as u64 > max_items => error(format!(
+                    "Expected at most {} items, got {}",
+                    max_items,
+                    items.len()
+                )),
+                _ => (),
+            },
+            Keyword::MinItems(min_items) => match value.as_array() {
+                Some(items) if (items.len() as u64) < min_items => error(format!(
+                    "Expected at least {} items, got {}",
+                    min_items,
+                    items.len()
+                )),
+                _ => (),
+            },
+            Keyword::UniqueItems => {
+                if let Some(items) = value.as_array() {
+                    let unique = items
+                        .iter()
+                        .enumerate()
+                        .all(|(i, item)| !items[..i].iter().any(|other| equal(item, other)));
+                    if !unique {
+                        error("Expected unique items".to_string());
+                    }
+                }
+            }
+            Keyword::Contains { schema, min, max } => {
+                if let Some(items) = value.as_array() {
+                    let count = items
+                        .iter()
+                        .filter(|item| self.is_valid_node(schema, item))
+                        .count() as u64;
+                    match max {
+                        _ if count < min => error(format!(
+                            "Expected at least {} matching items, got {}",
+                            min, count
+                        )),
+                        Some(max) if count > max => error(format!(
+                            "Expected at most {} matching items, got {}",
+                            max, count
+                        )),
+                        _ => (),
+                    }
+                }
+            }
+            Keyword::MaxProperties(max) => match value.as_object() {
+                Some(object) if object.len() as u64 > max => error(format!(
+                    "Expected at most {} properties, got {}",
+                    max,
+                    object.len()
+                )),
+                _ => (),
+            },
+            Keyword::MinProperties(min) => match value.as_object() {
+                Some(object) if (object.len() as u64) < min => error(format!(
+                    "Expected at least {} properties, got {}",
+                    min,
+                    object.len()
+                )),
+                _ => (),
+            },
+            Keyword::Required(ref required) => {
+                if let Some(object) = value.as_object() {
+                    for property in required {
+                        if !object.contains_key(property) {
+                            error(format!("Missing required property {:?}", property));
+                        }
+                    }
+                }
+            }
+            Keyword::Properties {
+                ref properties,
+                ref patterns,
+                additional,
+            } => {
+                if let Some(object) = value.as_object() {
+                    self.validate_properties(
+                        object, properties, patterns, additional, pointer, errors,
+                    );
+                }
+            }
+            Keyword::DependentRequired(ref dependencies) => {
+                if let Some(object) = value.as_object() {
+                    for (property, required) in dependencies {
+                        if !object.contains_key(property) {
+                            continue;
+                        }
+                        for dependency in required {
+                            if !object.contains_key(dependency) {
+                                error(format!(
+                                    "Missing property {:?} which {:?} depends on",
+                                    dependency, property
+                                ));
+                            }
+                        }
+                    }
+                }
+            }
+            Keyword::DependentSchemas(ref dependencies) => {
+                if let Some(object) = value.as_object() {
+                    for &(ref property, node) in dependencies {
+                        if object.contains_key(property) {
+                            self.validate_node(node, value, pointer, errors);
+                        }
+                    }
+                }
+            }
+            Keyword::PropertyNames(node) => {
+                if let Some(object) = value.as_object() {
+                    for key in object.keys() {
+                        let key_value = Value::String(key.clone());
+                        self.validate_node(node, &key_value, &child(pointer, key), errors);
+                    }
+                }
+            }
+            Keyword::AllOf(ref nodes) => {
+                for &node in nodes {
+                    self.validate_node(node, value, pointer, errors);
+                }
+            }
+            Keyword::AnyOf(ref nodes) => {
+                if !nodes.iter().any(|&node| self.is_valid_node(node, value)) {
+                    error("Expected to match at least one schema of `anyOf`".to_string());
+                }
+            }
+            Keyword::OneOf(ref nodes) => {
+                let matches = nodes
+                    .iter()
+                    .filter(|&&node| self.is_valid_node(node, value))
+                    .count();
+                if matches != 1 {
+                    error(format!(
+                        "Expected to match exactly one schema of `oneOf`, matched {}",
+                        matches
+                    ));
+                }
+            }
+            Keyword::Not(node) => {
+                if self.is_valid_node(node, value) {
+                    error("Expected not to match the schema of `not`".to_string());
+                }
+            }
+            Keyword::If { if_, then, else_ } => {
+                let node = if self.is_valid_node(if_, value) {
+                    then
+                } else {
+                    else_
+                };
+                if let Some(node) = node {
+                    self.validate_node(node, value, pointer, errors);
+                }
+            }
+        }
+    }
+
+    fn validate_properties(
+        &self,
+        object: &Map<String, Value>,
+        properties: &[(String, usize)],
+        patterns: &[(Regex, usize)],
+        additional: Option<usize>,
+        pointer: &str,
+        errors: &mut ValidationErrors,
+    ) {
+        for (key, value) in object {
+            let pointer = child(pointer, key);
+            let mut matched = false;
+            for &(ref property, node) in properties {
+                if property == key {
+                    matched = true;
+                    self.validate_node(node, value, &pointer, errors);
+                }
+            }
+            for &(ref regex, node) in patterns {
+                if regex.is_match(key) {
+                    matched = true;
+                    self.validate_node(node, value, &pointer, errors);
+                }
+            }
+            match additional {
+                Some(node) if !matched => self.validate_node(node, value, &pointer, errors),
+                _ => (),
+            }
+        }
+    }
+}
+
+struct Compiler {
+    draft: Draft,
+    nodes: Vec<Vec<Keyword>>,
+    /// The node of every schema by its URI (`document#pointer` or `document#anchor`)
+    uris: HashMap<String, usize>,
+    documents: HashMap<String, Value>,
+    compiled: HashSet<String>,
+    /// Nodes which contain a `Keyword::Ref`
+    unlinked: Vec<usize>,
+}
+
+impl Compiler {
+    /// Compiles the schema `value` located at `pointer` within the document with the base URI
+    /// `base`.
+    fn compile(&mut self, value: &Value, base: &str, pointer: &str) -> Result<usize, SchemaError> {
+        let node = self.nodes.len();
+        self.nodes.push(Vec::new());
+        self.uris
+            .entry(format!("{}#{}", base, pointer))
+            .or_insert(node);
+
+        let object = match *value {
+            Value::Bool(true) => return Ok(node),
+            Value::Bool(false) => {
+                self.nodes[node].push(Keyword::False);
+                return Ok(node);
+            }
+            Value::Object(ref object) => object,
+            _ => {
+                return Err(SchemaError::new(
+                    format!("{}#{}", base, pointer),
+                    "Expected a schema",
+                ))
+            }
+        };
+
+        // Before 2019-09 `$ref` overrides every other keyword, including `$id`, but references
+        // may still point into the subschemas besides it
+        if let (Some(ref_), true) = (
+            object.get("$ref").and_then(Value::as_str),
+            self.draft <= Draft::Draft7,
+        ) {
+            self.compile_keywords(object, base, pointer)?;
+            self.nodes[node] = vec![Keyword::Ref(absolute(base, ref_))];
+            self.unlinked.push(node);
+            return Ok(node);
+        }
+
+        let id_keyword = if self.draft == Draft::Draft4 {
+            "id"
+        } else {
+            "$id"
+        };
+        let mut base = base.to_string();
+        let mut pointer = pointer.to_string();
+        if let Some(id) = object.get(id_keyword).and_then(Value::as_str) {
+            let (document, fragment) = split_ref(id);
+            if !document.is_empty() {
+                base = resolve_uri(&base, document);
+                pointer = String::new();
+                self.uris.insert(format!("{}#", base), node);
+            }
+            if !fragment.is_empty() && !fragment.starts_with('/') {
+                self.uris.insert(format!("{}#{}", base, fragment), node);
+            }
+        }
+        for anchor in &["$anchor", "$dynamicAnchor"] {
+            if let Some(anchor) = object.get(*anchor).and_then(Value::as_str) {
+                self.uris.insert(format!("{}#{}", base, anchor), node);
+            }
+        }
+
+        let keywords = self.compile_keywords(object, &base, &pointer)?;
+        if keywords.iter().any(|k| matches!(k, Keyword::Ref(_))) {
+            self.unlinked.push(node);
+        }
+        self.nodes[node] = keywords;
+        Ok(node)
+    }
+
+    fn compile_keywords(
+        &mut self,
+        object: &Map<String, Value>,
+        base: &str,
+        pointer: &str,
+    ) -> Result<Vec<Keyword>, SchemaError> {
+        let draft = self.draft;
+        let mut keywords = Vec::new();
+
+        for keyword in &["definitions", "$defs"] {
+            if let Some(Value::Object(definitions)) = object.get(*keyword) {
+                for (name, definition) in definitions {
+                    let pointer = child(&child(pointer, keyword), name);
+                    self.compile(definition, base, &pointer)?;
+                }
+            }
+        }
+
+        for keyword in &["$ref", "$recursiveRef", "$dynamicRef"] {
+            if let Some(ref_) = object.get(*keyword).and_then(Value::as_str) {
+                keywords.push(Keyword::Ref(absolute(base, ref_)));
+            }
+        }
+
+        match object.get("type") {
+            Some(Value::String(name)) => {
+                keywords.extend(Type::from_name(name).map(|typ| Keyword::Type(vec![typ])))
+            }
+            Some(Value::Array(names)) => keywords.push(Keyword::Type(
+                names
+                    .iter()
+                    .filter_map(|name| Type::from_name(name.as_str()?))
+                    .collect(),
+            )),
+            _ => (),
+        }
+        if let Some(Value::Array(values)) = object.get("enum") {
+            keywords.push(Keyword::Enum(values.clone()));
+        }
+        if let (Some(constant), true) = (object.get("const"), draft >= Draft::Draft6) {
+            keywords.push(Keyword::Const(constant.clone()));
+        }
+
+        let number = |keyword: &str| object.get(keyword).and_then(Value::as_f64);
+        let count = |keyword: &str| number(keyword).map(|n| n as u64);
+        keywords.extend(number("multipleOf").map(Keyword::MultipleOf));
+        if draft == Draft::Draft4 {
+            let exclusive = |keyword: &str| object.get(keyword) == Some(&Value::Bool(true));
+            keywords.extend(number("maximum").map(if exclusive("exclusiveMaximum") {
+                Keyword::ExclusiveMaximum
+            } else {
+                Keyword::Maximum
+            }));
+            keywords.extend(number("minimum").map(if exclusive("exclusiveMinimum") {
+                Keyword::ExclusiveMinimum
+            } else {
+                Keyword::Minimum
+            }));
+        } else {
+            keywords.extend(number("maximum").map(Keyword::Maximum));
+            keywords.extend(number("exclusiveMaximum").map(Keyword::ExclusiveMaximum));
+            keywords.extend(number("minimum").map(Keyword::Minimum));
+            keywords.extend(number("exclusiveMinimum").map(Keyword::ExclusiveMinimum));
+        }
+        keywords.extend(count("maxLength").map(Keyword::MaxLength));
+        keywords.extend(count("minLength").map(Keyword::MinLength));
+        if let Some(pattern) = object.get("pattern").and_then(Value::as_str) {
+            keywords.push(Keyword::Pattern(regex(
+                pattern,
+                base,
+                &child(pointer, "pattern"),
+            )?));
+        }
+
+        let (prefix, rest) = if draft >= Draft::Draft202012 {
+            (object.get("prefixItems"), object.get("items"))
+        } else {
+            match object.get("items") {
+                Some(items @ Value::Array(_)) => (Some(items), object.get("additionalItems")),
+                items => (None, items),
+            }
+        };
+        let prefix = match prefix {
+            Some(Value::Array(prefix)) => prefix
+                .iter()
+                .enumerate()
+                .map(|(i, item)| {
+                    let keyword = if draft >= Draft::Draft202012 {
+                        "prefixItems"
+                    } else {
+                        "items"
+                    };
+                    self.compile(item, base, &child(&child(pointer, keyword), &i.to_string()))
+                })
+                .collect::<Result<Vec<_>, _>>()?,
+            _ => Vec::new(),
+        };
+        let rest = match rest {
+            Some(rest) => {
+                let keyword = if prefix.is_empty() || draft >= Draft::Draft202012 {
+                    "items"
+                } else {
+                    "additionalItems"
+                };
+                Some(self.compile(rest, base, &child(pointer, keyword))?)
+            }
+            None => None,
+        };
+        if !prefix.is_empty() || rest.is_some() {
+            keywords.push(Keyword::Items { prefix, rest });
+        }
+        keywords.extend(count("maxItems").map(Keyword::MaxItems));
+        keywords.extend(count("minItems").map(Keyword::MinItems));
+        if object.get("uniqueItems") == Some(&Value::Bool(true)) {
+            keywords.push(Keyword::UniqueItems);
+        }
+        if let (Some(contains), true) = (object.get("contains"), draft >= Draft::Draft6) {
+            let schema = self.compile(contains, base, &child(pointer, "contains"))?;
+            let (min, max) = if draft >= Draft::Draft201909 {
+                (count("minContains").unwrap_or(1), count("maxContains"))
+            } else {
+                (1, None)
+            };
+            keywords.push(Keyword::Contains { schema, min, max });
+        }
+
+        keywords.extend(count("maxProperties").map(Keyword::MaxProperties));
+        keywords.extend(count("minProperties").map(Keyword::MinProperties));
+        if let Some(Value::Array(required)) = object.get("required") {
+            keywords.push(Keyword::Required(strings(required)));
+        }
+        let properties = self.compile_map(object.get("properties"), base, pointer, "properties")?;
+        let patterns = self
+            .compile_map(
+                object.get("patternProperties"),
+                base,
+                pointer,
+                "patternProperties",
+            )?
+            .into_iter()
+            .map(|(pattern, node)| {
+                let pointer = child(&child(pointer, "patternProperties"), &pattern);
+                Ok((regex(&pattern, base, &pointer)?, node))
+            })
+            .collect::<Result<Vec<_>, SchemaError>>()?;
+        let additional = match object.get("additionalProperties") {
+            Some(additional) => {
+                Some(self.compile(additional, base, &child(pointer, "additionalProperties"))?)
+            }
+            None => None,
+        };
+        if !properties.is_empty() || !patterns.is_empty() || additional.is_some() {
+            keywords.push(Keyword::Properties {
+                properties,
+                patterns,
+                additional,
+            });
+        }
+
+        let mut dependent_required = Vec::new();
+        let mut dependent_schemas = Vec::new();
+        if draft <= Draft::Draft7 {
+            if let Some(Value::Object(dependencies)) = object.get("dependencies") {
+                for (property, dependency) in dependencies {
+                    match dependency {
+                        Value::Array(required) => {
+                            dependent_required.push((property.clone(), strings(required)))
+                        }
+                        _ => {
+                            let pointer = child(&child(pointer, "dependencies"), property);
+                            let node = self.compile(dependency, base, &pointer)?;
+                            dependent_schemas.push((property.clone(), node));
+                        }
+                    }
+                }
+            }
+        } else {
+            if let Some(Value::Object(dependencies)) = object.get("dependentRequired") {
+                for (property, required) in dependencies {
+                    if let Value::Array(required) = required {
+                        dependent_required.push((property.clone(), strings(required)));
+                    }
+                }
+            }
+            dependent_schemas = self.compile_map(
+                object.get("dependentSchemas"),
+                base,
+                pointer,
+                "dependentSchemas",
+            )?;
+        }
+        if !dependent_required.is_empty() {
+            keywords.push(Keyword::DependentRequired(dependent_required));
+        }
+        if !dependent_schemas.is_empty() {
+            keywords.push(Keyword::DependentSchemas(dependent_schemas));
+        }
+        if let (Some(names), true) = (object.get("propertyNames"), draft >= Draft::Draft6) {
+            let node = self.compile(names, base, &child(pointer, "propertyNames"))?;
+            keywords.push(Keyword::PropertyNames(node));
+        }
+
+        for &(keyword, constructor) in &[
+            ("allOf", Keyword::AllOf as fn(Vec<usize>) -> Keyword),
+            ("anyOf", Keyword::AnyOf),
+            ("oneOf", Keyword::OneOf),
+        ] {
+            if let Some(Value::Array(schemas)) = object.get(keyword) {
+                let nodes = schemas
+                    .iter()
+                    .enumerate()
+                    .map(|(i, schema)| {
+                        self.compile(
+                            schema,
+                            base,
+                            &child(&child(pointer, keyword), &i.to_string()),
+                        )
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                keywords.push(constructor(nodes));
+            }
+        }
+        if let Some(not) = object.get("not") {
+            keywords.push(Keyword::Not(self.compile(
+                not,
+                base,
+                &child(pointer, "not"),
+            )?));
+        }
+        if let (Some(if_), true) = (object.get("if"), draft >= Draft::Draft7) {
+            let if_ = self.compile(if_, base, &child(pointer, "if"))?;
+            let mut branch = |keyword: &str| match object.get(keyword) {
+                Some(schema) => self
+                    .compile(schema, base, &child(pointer, keyword))
+                    .map(Some),
+                None => Ok(None),
+            };
+            let then = branch("then")?;
+            let else_ = branch("else")?;
+            keywords.push(Keyword::If { if_, then, else_ });
+        }
+        Ok(keywords)
+    }
+
+    /// Compiles the schemas of a keyword such as `properties` which maps names to schemas.
+    fn compile_map(
+        &mut self,
+        value: Option<&Value>,
+        base: &str,
+        pointer: &str,
+        keyword: &str,
+    ) -> Result<Vec<(String, usize)>, SchemaError> {
+        let schemas = match value {
+            Some(Value::Object(schemas)) => schemas,
+            _ => return Ok(Vec::new()),
+        };
+        schemas
+            .iter()
+            .map(|(name, schema)| {
+                let node = self.compile(schema, base, &child(&child(pointer, keyword), name))?;
+                Ok((name.clone(), node))
+            })
+            .collect()
+    }
+
+    /// Replaces every `Keyword::Ref` with a link to the node it refers to, compiling the
+    /// referenced schemas which have not been compiled yet.
+    fn link(&mut self) -> Result<(), SchemaError> {
+        while let Some(node) = self.unlinked.pop() {
+            for i in 0..self.nodes[node].len() {
+                if let Keyword::Ref(ref uri) = self.nodes[node][i] {
+                    let uri = uri.clone();
+                    let target = self.resolve(&uri)?;
+                    self.nodes[node][i] = Keyword::Link(target);
+                }
+            }
+        }
+        self.check_cycles()
+    }
+
+    /// Returns an error if a schema applies to itself through a cycle of references and
+    /// keywords such as `allOf` which do not move on to a part of the value, as validating it
+    /// would never end.
+    fn check_cycles(&self) -> Result<(), SchemaError> {
+        // 0: not visited, 1: on the path being searched, 2: done
+        let mut states = vec![0u8; self.nodes.len()];
+        for start in 0..self.nodes.len() {
+            if states[start] != 0 {
+                continue;
+            }
+            states[start] = 1;
+            let mut path = vec![(start, in_place(&self.nodes[start]))];
+            while let Some((node, successors)) = path.last_mut() {
+                let node = *node;
+                match successors.pop() {
+                    Some(next) if states[next] == 1 => {
+                        let uri = self
+                            .uris
+                            .iter()
+                            .filter(|&(_, &n)| n == next)
+                            .map(|(uri, _)| uri)
+                            .min()
+                            .map_or("", |uri| &uri[..]);
+                        return Err(SchemaError::new(
+                            uri,
+                            "The schema applies to itself without validating a part of the value",
+                        ));
+                    }
+                    Some(next) if states[next] == 0 => {
+                        states[next] = 1;
+                        path.push((next, in_place(&self.nodes[next])));
+                    }
+                    Some(_) => (),
+                    None => {
+                        states[node] = 2;
+                        path.pop();
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn resolve(&mut self, uri: &str) -> Result<usize, SchemaError> {
+        if let Some(&node) = self.uris.get(uri) {
+            return Ok(node);
+        }
+        let (document, fragment) = split_ref(uri);
+        // Compiling a document registers all of its schemas and `id`s
+        if !self.compiled.contains(document) {
+            if let Some(value) = self.documents.get(document).cloned() {
+                self.compiled.insert(document.to_string());
+                let draft = std::mem::replace(&mut self.draft, Draft::of(&value));
+                let result = self.compile(&value, document, "");
+                self.draft = draft;
+                result?;
+                if let Some(&node) = self.uris.get(uri) {
+                    return Ok(node);
+                }
+            }
+        }
+        // Pointers to schemas which are not below a known keyword
+        let value = self
+            .documents
+            .get(document)
+            .and_then(|value| value.pointer(&percent_decode(fragment)))
+            .filter(|_| fragment.is_empty() || fragment.starts_with('/'))
+            .cloned()
+            .ok_or_else(|| SchemaError::new(uri, "Unable to resolve reference"))?;
+        self.compile(&value, document, fragment)
+    }
+}
+
+/// Returns the nodes which `keywords` apply to the value itself rather than to a part of it.
+fn in_place(keywords: &[Keyword]) -> Vec<usize> {
+    let mut nodes = Vec::new();
+    for keyword in keywords {
+        match *keyword {
+            Keyword::Link(node) | Keyword::Not(node) => nodes.push(node),
+            Keyword::AllOf(ref all) | Keyword::AnyOf(ref all) | Keyword::OneOf(ref all) => {
+                nodes.extend(all)
+            }
+            Keyword::DependentSchemas(ref dependencies) => {
+                nodes.extend(dependencies.iter().map(|&(_, node)| node))
+            }
+            Keyword::If { if_, then, else_ } => {
+                nodes.extend(Some(if_).into_iter().chain(then).chain(else_))
+            }
+            _ => (),
+        }
+    }
+    nodes
+}
+
+/// Resolves the reference `ref_` against `base`, always including a (possibly empty) fragment.
+fn absolute(base: &str, ref_: &str) -> String {
+    let (document, fragment) = split_ref(ref_);
+    format!("{}#{}", resolve_uri(base, document), fragment)
+}
+
+/// Compiles the `pattern` located at `pointer`, which the `regex` crate may not support.
+fn regex(pattern: &str, base: &str, pointer: &str) -> Result<Regex, SchemaError> {
+    Regex::new(pattern).map_err(|err| {
+        SchemaError::new(
+            format!("{}#{}", base, pointer),
+            format!("Unsupported pattern: {}", err),
+        )
+    })
+}
+
+fn strings(values: &[Value]) -> Vec<String> {
+    values
+        .iter()
+        .filter_map(|value| value.as_str().map(String::from))
+        .collect()
+}
+
+fn is_type(typ: Type, value: &Value) -> bool {
+    match (typ, value) {
+        (Type::Array, Value::Array(_))
+        | (Type::Boolean, Value::Bool(_))
+        | (Type::Null, Value::Null)
+        | (Type::Number, Value::Number(_))
+        | (Type::Object, Value::Object(_))
+        | (Type::String, Value::String(_)) => true,
+        (Type::Integer, Value::Number(n)) => {
+            n.is_i64() || n.is_u64() || matches!(n.as_f64(), Some(n) if n.fract() == 0.0)
+        }
+        _ => false,
+    }
+}
+
+/// Compares JSON values, treating numbers as equal if they have the same value (`1 == 1.0`).
+fn equal(a: &Value, b: &Value) -> bool {
+    match (a, b) {
+        (Value::Number(a), Value::Number(b)) => match (a.as_i64(), b.as_i64()) {
+            (Some(a), Some(b)) => a == b,
+            _ => a.as_f64() == b.as_f64(),
+        },
+        (Value::Array(a), Value::Array(b)) => {
+            a.len() == b.len() && a.iter().zip(b).all(|(a, b)| equal(a, b))
+        }
+        (Value::Object(a), Value::Object(b)) => {
+            a.len() == b.len()
+                && a.iter()
+                    .all(|(key, a)| matches!(b.get(key), Some(b) if equal(a, b)))
+        }
+        _ => a == b,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use serde_json::json;
+
+    fn errors(schema: Value, value: Value) -> Vec<String> {
+        match Validator::new(&schema).unwrap().validate(&value) {
+            Ok(()) => Vec::new(),
+            Err(errors) => errors.iter().map(|error| error.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn keywords() {
+        let schema = json!({
+            "type": "object",
+            "required": ["name"],
+            "properties": {
+                "name": { "type": "string", "maxLength": 3 },
+                "tags": { "type": "array", "items": { "enum": ["a", "b"] }, "uniqueItems": true }
+            },
+            "additionalProperties": false
+        });
+        assert!(errors(schema.clone(), json!({ "name": "ann", "tags": ["a"] })).is_empty());
+        assert_eq!(
+            errors(
+                schema,
+                json!({ "name": "anna", "tags": ["a", "c", "a"], "other": 1 })
+            ),
+            [
+                r#"/name: "anna" is longer than 3 characters"#,
+                "/other: No value is allowed",
+                r#"/tags/1: "c" is not one of the allowed values"#,
+                "/tags: Expected unique items",
+            ]
+        );
+    }
+
+    #[test]
+    fn drafts() {
+        let draft4 = json!({ "maximum": 3, "exclusiveMaximum": true });
+        assert_eq!(
+            errors(draft4, json!(3)),
+            [": 3 is greater than or equal to the exclusive maximum of 3"]
+        );
+        let draft6 = json!({
+            "$schema": "http://json-schema.org/draft-06/schema#",
+            "exclusiveMaximum": 3
+        });
+        assert_eq!(errors(draft6.clone(), json!(2)), Vec::<String>::new());
+        assert_eq!(errors(draft6, json!(3)).len(), 1);
+    }
+
+    #[test]
+    fn references() {
+        let schema = json!({
+            "id": "http://example.com/root.json",
+            "definitions": {
+                "node": {
+                    "type": "object",
+                    "properties": { "next": { "$ref": "#/definitions/node" } },
+                    "required": ["value"]
+                },
+                "id": { "id": "#id", "type": "integer" }
+            },
+            "properties": {
+                "first": { "$ref": "#/definitions/node" },
+                "id": { "$ref": "#id" },
+                "remote": { "$ref": "other.json#/definitions/positive" }
+            }
+        });
+        let other = json!({ "definitions": { "positive": { "minimum": 0 } } });
+        let validator =
+            Validator::with_documents("", &schema, vec![("http://example.com/other.json", &other)])
+                .unwrap();
+        assert!(validator.is_valid(&json!({ "first": { "value": 1, "next": { "value": 2 } } })));
+        assert!(!validator.is_valid(&json!({ "first": { "value": 1, "next": {} } })));
+        assert!(!validator.is_valid(&json!({ "id": "1" })));
+        assert!(!validator.is_valid(&json!({ "remote": -1 })));
+
+        let err = Validator::new(&json!({ "$ref": "missing.json" })).unwrap_err();
+        assert_eq!(err.uri(), "missing.json#");
+    }
+
+    #[test]
+    fn ref_siblings() {
+        // The `$id` of a definition beside a `$ref` which overrides it still identifies it
+        let schema = json!({
+            "$schema": "http://json-schema.org/draft-07/schema#",
+            "$ref": "#/definitions/a",
+            "definitions": {
+                "a": { "$ref": "item.json" },
+                "b": { "$id": "item.json", "type": "integer" }
+            }
+        });
+        let validator = Validator::new(&schema).unwrap();
+        assert!(validator.is_valid(&json!(1)));
+        assert!(!validator.is_valid(&json!("1")));
+    }
+
+    #[test]
+    fn cycles() {
+        let err = Validator::new(&json!({ "$ref": "#" })).unwrap_err();
+        assert_eq!(err.uri(), "#");
+
+        let err = Validator::new(&json!({
+            "$schema": "https://json-schema.org/draft/2020-12/schema",
+            "$defs": {
+                "a": { "$ref": "#/$defs/b" },
+                "b": { "anyOf": [{ "$ref": "#/$defs/a" }] }
+            }
+        }))
+        .unwrap_err();
+        assert_eq!(err.uri(), "#/$defs/a");
+
+        // Recursing into a part of the value ends
+        let schema = json!({ "properties": { "next": { "$ref": "#" } }, "maxProperties": 1 });
+        let validator = Validator::new(&schema).unwrap();
+        assert!(validator.is_valid(&json!({ "next": { "next": {} } })));
+        assert!(!validator.is_valid(&json!({ "next": { "next": {}, "other": 1 } })));
+    }
+
+    #[test]
+    fn unsupported_patterns() {
+        let err =
+            Validator::new(&json!({ "properties": { "a": { "pattern": "(?!a)" } } })).unwrap_err();
+        assert_eq!(err.uri(), "#/properties/a/pattern");
+
+        let err = Validator::new(&json!({ "patternProperties": { "^(?<=a)": {} } })).unwrap_err();
+        assert_eq!(err.uri(), "#/patternProperties/^(?<=a)");
+    }
+
+    #[test]
+    fn numbers_compare_by_value() {
+        assert!(equal(&json!([1, { "a": 2.0 }]), &json!([1.0, { "a": 2 }])));
+        assert!(!equal(&json!(1), &json!(true)));
+    }
+}
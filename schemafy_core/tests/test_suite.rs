@@ -0,0 +1,141 @@
+//! Runs the validator against every test of the JSON Schema Test Suite, which is checked out as
+//! a git submodule in `tests/JSON-Schema-Test-Suite` of the workspace.
+
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use serde_json::Value;
+
+use schemafy_core::validator::Validator;
+
+/// The drafts of the test suite along with the `$schema` URI identifying them and the test files
+/// which are skipped for them.
+///
+/// A draft is only run once a skip list built by running its tests has been added, as the later
+/// drafts exercise keywords which the validator does not support (such as `unevaluatedItems`).
+const DRAFTS: &[(&str, &str, &[&str])] =
+    &[("draft4", "http://json-schema.org/draft-04/schema#", &[])];
+
+/// Test groups which refer to the meta-schemas of the drafts, which are not part of the suite
+/// (except for draft 4 whose meta-schema is `schemafy_lib/src/schema.json`).
+fn refers_to_meta_schema(draft: &str, schema: &Value) -> bool {
+    draft != "draft4" && schema.to_string().contains("json-schema.org/draft")
+}
+
+fn suite_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("../tests/JSON-Schema-Test-Suite")
+}
+
+/// Reads the documents below `directory` as the documents at `prefix` followed by their path.
+fn read_documents(prefix: &str, directory: &Path, documents: &mut BTreeMap<String, Value>) {
+    for entry in fs::read_dir(directory).unwrap() {
+        let path = entry.unwrap().path();
+        let uri = format!(
+            "{}{}",
+            prefix,
+            path.file_name().unwrap().to_string_lossy()
+        );
+        if path.is_dir() {
+            read_documents(&format!("{}/", uri), &path, documents);
+        } else if path
+            .extension()
+            .is_some_and(|extension| extension == "json")
+        {
+            let json = fs::read_to_string(&path).unwrap();
+            documents.insert(uri, serde_json::from_str(&json).unwrap());
+        }
+    }
+}
+
+#[test]
+fn json_schema_test_suite() {
+    let suite_dir = suite_dir();
+    assert!(
+        suite_dir.join("tests").exists(),
+        "The JSON Schema Test Suite is not checked out in {} (git submodule update --init)",
+        suite_dir.display()
+    );
+
+    let mut documents = BTreeMap::new();
+    read_documents(
+        "http://localhost:1234/",
+        &suite_dir.join("remotes"),
+        &mut documents,
+    );
+    let meta_schema = fs::read_to_string(
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("../schemafy_lib/src/schema.json"),
+    )
+    .unwrap();
+    documents.insert(
+        "http://json-schema.org/draft-04/schema".to_string(),
+        serde_json::from_str(&meta_schema).unwrap(),
+    );
+
+    let mut failures = Vec::new();
+    let mut count = 0;
+    for &(draft, uri, skipped) in DRAFTS {
+        let mut paths = fs::read_dir(suite_dir.join("tests").join(draft))
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| {
+                path.extension()
+                    .is_some_and(|extension| extension == "json")
+            })
+            .collect::<Vec<_>>();
+        paths.sort();
+        for path in paths {
+            let name = path.file_stem().unwrap().to_string_lossy().into_owned();
+            if skipped.contains(&&name[..]) {
+                continue;
+            }
+            let groups: Vec<Value> =
+                serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+            for group in groups {
+                let mut schema = group["schema"].clone();
+                if refers_to_meta_schema(draft, &schema) {
+                    continue;
+                }
+                // The test schemas do not declare which draft they are written against
+                if let Some(schema) = schema.as_object_mut() {
+                    schema
+                        .entry("$schema")
+                        .or_insert_with(|| Value::String(uri.to_string()));
+                }
+                let description = format!("{}/{}: {}", draft, name, group["description"]);
+                let validator = match Validator::with_documents(
+                    "",
+                    &schema,
+                    documents.iter().map(|(uri, document)| (&uri[..], document)),
+                ) {
+                    Ok(validator) => validator,
+                    Err(err) => {
+                        failures.push(format!("{}: {}", description, err));
+                        continue;
+                    }
+                };
+                for test in group["tests"].as_array().unwrap() {
+                    count += 1;
+                    let valid = test["valid"].as_bool().unwrap();
+                    if validator.is_valid(&test["data"]) != valid {
+                        failures.push(format!(
+                            "{} / {}: expected {}",
+                            description,
+                            test["description"],
+                            if valid { "valid" } else { "invalid" }
+                        ));
+                    }
+                }
+            }
+        }
+    }
+    assert!(
+        failures.is_empty(),
+        "{} of {} tests failed:\n{}",
+        failures.len(),
+        count,
+        failures.join("\n")
+    );
+}
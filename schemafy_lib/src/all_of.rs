@@ -0,0 +1,825 @@
+//! Intersection of the schemas of an `allOf`, so that the type of the values which all of them
+//! accept can be generated from a single schema.
+
+use std::collections::BTreeMap;
+
+use serde_json::Value;
+
+use crate::{is_false, ExclusiveLimit, Items, Schema, SimpleTypes};
+
+/// Merges `r` into `result`, so that `result` accepts the values which both accepted. Both
+/// schemas must have their `$ref` and `allOf` resolved already, subschemas with a `$ref` or an
+/// `allOf` are instead combined into an `allOf` of their own which is merged once it is expanded.
+///
+/// Returns an error if no value is accepted by both. Keywords which can not be expressed by a
+/// single schema, such as two differing `pattern`s or `oneOf`s, are kept from `result` while the
+/// ones of `r` are moved into an `allOf` of `result`, which holds the parts left over by merging.
+pub(crate) fn merge(result: &mut Schema, r: &Schema) -> Result<(), String> {
+    let mut rest = Schema::default();
+    // Annotations of later schemas are more specific
+    override_option(&mut result.description, &r.description);
+    override_option(&mut result.title, &r.title);
+    override_option(&mut result.default, &r.default);
+    fill_option(&mut result.examples, &r.examples);
+    fill_option(&mut result.content_encoding, &r.content_encoding);
+    fill_option(&mut result.content_media_type, &r.content_media_type);
+    fill_option(&mut result.format, &r.format);
+    merge_flag(&mut result.read_only, r.read_only);
+    merge_flag(&mut result.write_only, r.write_only);
+    for (name, definition) in r.definitions.iter() {
+        result
+            .definitions
+            .entry(name.clone())
+            .or_insert_with(|| definition.clone());
+    }
+    for (name, definition) in r._defs.iter() {
+        result
+            ._defs
+            .entry(name.clone())
+            .or_insert_with(|| definition.clone());
+    }
+
+    merge_types(&mut result.type_, &r.type_)?;
+    merge_enum(result, r)?;
+
+    merge_numbers(result, r)?;
+    merge_lengths(
+        "length",
+        &mut result.min_length,
+        &mut result.max_length,
+        r.min_length,
+        r.max_length,
+    )?;
+    merge_lengths(
+        "items",
+        &mut result.min_items,
+        &mut result.max_items,
+        r.min_items,
+        r.max_items,
+    )?;
+    merge_lengths(
+        "properties",
+        &mut result.min_properties,
+        &mut result.max_properties,
+        r.min_properties,
+        r.max_properties,
+    )?;
+    // Regular expressions without look-around can not require two patterns at once
+    keep_both(&mut result.pattern, &r.pattern, &mut rest.pattern);
+    merge_flag(&mut result.unique_items, r.unique_items);
+
+    merge_items(result, r)?;
+    if let Some(ref prefix_items) = r.prefix_items {
+        merge_positions(
+            result.prefix_items.get_or_insert_with(Vec::new),
+            prefix_items,
+        )?;
+    }
+    merge_boxed(&mut result.additional_items, &r.additional_items)?;
+    merge_boxed(&mut result.unevaluated_items, &r.unevaluated_items)?;
+    keep_both(&mut result.contains, &r.contains, &mut rest.contains);
+
+    merge_properties(result, r)?;
+    merge_boxed(
+        &mut result.unevaluated_properties,
+        &r.unevaluated_properties,
+    )?;
+    merge_boxed(&mut result.property_names, &r.property_names)?;
+    if let Some(ref required) = r.required {
+        let result = result.required.get_or_insert_with(Vec::new);
+        for name in required {
+            if !result.contains(name) {
+                result.push(name.clone());
+            }
+        }
+    }
+    if let Some(ref dependent_required) = r.dependent_required {
+        let result = result.dependent_required.get_or_insert_with(BTreeMap::new);
+        for (name, required) in dependent_required {
+            let result = result.entry(name.clone()).or_default();
+            for name in required {
+                if !result.contains(name) {
+                    result.push(name.clone());
+                }
+            }
+        }
+    }
+    check_required(result)?;
+    merge_subschemas(&mut result.dependent_schemas, &r.dependent_schemas)?;
+    if let Some(ref dependencies) = r.dependencies {
+        let result = result.dependencies.get_or_insert_with(BTreeMap::new);
+        for (name, dependency) in dependencies {
+            result
+                .entry(name.clone())
+                .or_insert_with(|| dependency.clone());
+        }
+    }
+
+    // Neither of two schemas is the same as not either of them
+    result.not = match (result.not.take(), &r.not) {
+        (Some(not), Some(r_not)) if *not != **r_not => Some(Box::new(Schema {
+            any_of: Some(vec![*not, (**r_not).clone()]),
+            ..Schema::default()
+        })),
+        (not, r_not) => not.or_else(|| r_not.clone()),
+    };
+    keep_both(&mut result.any_of, &r.any_of, &mut rest.any_of);
+    keep_both(&mut result.one_of, &r.one_of, &mut rest.one_of);
+    let conditional = |schema: &Schema| {
+        (
+            schema.if_.clone(),
+            schema.then.clone(),
+            schema.else_.clone(),
+        )
+    };
+    if result.if_.is_none() {
+        result.if_ = r.if_.clone();
+        result.then = r.then.clone();
+        result.else_ = r.else_.clone();
+    } else if r.if_.is_some() && conditional(result) != conditional(r) {
+        rest.if_ = r.if_.clone();
+        rest.then = r.then.clone();
+        rest.else_ = r.else_.clone();
+    }
+
+    if let Some(ref all_of) = r.all_of {
+        result
+            .all_of
+            .get_or_insert_with(Vec::new)
+            .extend(all_of.iter().cloned());
+    }
+    if rest != Schema::default() {
+        result.all_of.get_or_insert_with(Vec::new).push(rest);
+    }
+    Ok(())
+}
+
+/// Merges `properties`, `patternProperties` and `additionalProperties`. A property which only one
+/// of the schemas lists must also satisfy the `additionalProperties` of the other one, unless one
+/// of the other's `patternProperties` matches its name. Names matching a pattern of only one of
+/// the schemas are given the `additionalProperties` of the other one as well, which is exact as
+/// long as the other one has no patterns which match them.
+fn merge_properties(result: &mut Schema, r: &Schema) -> Result<(), String> {
+    let mut r_properties = r.properties.clone();
+    for (name, schema) in r_properties.iter_mut() {
+        if !result.properties.contains_key(name) {
+            if let Some(additional) = additional_schema(result, name) {
+                merge_subschema(schema, additional).map_err(|err| format!("{}: {}", name, err))?;
+            }
+        }
+    }
+    for (name, schema) in result.properties.iter_mut() {
+        if !r.properties.contains_key(name) {
+            if let Some(additional) = additional_schema(r, name) {
+                merge_subschema(schema, additional).map_err(|err| format!("{}: {}", name, err))?;
+            }
+        }
+    }
+    merge_subschemas(&mut result.properties, &r_properties)?;
+
+    let mut r_patterns = r.pattern_properties.clone();
+    for (pattern, schema) in r_patterns.iter_mut() {
+        match result.additional_properties {
+            Some(ref additional) if !result.pattern_properties.contains_key(pattern) => {
+                merge_subschema(schema, additional)
+                    .map_err(|err| format!("{}: {}", pattern, err))?
+            }
+            _ => (),
+        }
+    }
+    for (pattern, schema) in result.pattern_properties.iter_mut() {
+        match r.additional_properties {
+            Some(ref additional) if !r.pattern_properties.contains_key(pattern) => {
+                merge_subschema(schema, additional)
+                    .map_err(|err| format!("{}: {}", pattern, err))?
+            }
+            _ => (),
+        }
+    }
+    merge_subschemas(&mut result.pattern_properties, &r_patterns)?;
+    merge_boxed(&mut result.additional_properties, &r.additional_properties)
+}
+
+/// Returns the `additionalProperties` which a property `name` must satisfy if `schema` does not
+/// list it in `properties`.
+fn additional_schema<'a>(schema: &'a Schema, name: &str) -> Option<&'a Schema> {
+    let matched = schema
+        .pattern_properties
+        .keys()
+        .any(|pattern| regex::Regex::new(pattern).is_ok_and(|pattern| pattern.is_match(name)));
+    if matched {
+        None
+    } else {
+        schema.additional_properties.as_deref()
+    }
+}
+
+/// Returns an error if a required property is not allowed, and drops the optional properties
+/// which are not allowed anyway.
+fn check_required(result: &mut Schema) -> Result<(), String> {
+    let required = result.required.clone().unwrap_or_default();
+    for name in &required {
+        let schema = result
+            .properties
+            .get(name)
+            .or_else(|| additional_schema(result, name));
+        if schema.is_some_and(is_false) {
+            return Err(format!("the required property `{}` is not allowed", name));
+        }
+    }
+    let forbidden = result
+        .properties
+        .iter()
+        .filter(|&(name, schema)| {
+            is_false(schema)
+                && !required.contains(name)
+                && additional_schema(result, name).is_some_and(is_false)
+        })
+        .map(|(name, _)| name.clone())
+        .collect::<Vec<_>>();
+    for name in forbidden {
+        result.properties.remove(&name);
+    }
+    Ok(())
+}
+
+/// Returns true if `schema` accepts every value, having nothing but annotations such as
+/// `description` or `default` (or a `type` listing every type).
+pub(crate) fn is_unconstrained(schema: &Schema) -> bool {
+    use SimpleTypes::*;
+
+    let every_type = [Array, Boolean, Null, Number, Object, String]
+        .iter()
+        .all(|typ| schema.type_.contains(typ));
+    Schema {
+        type_: if every_type {
+            Vec::new()
+        } else {
+            schema.type_.clone()
+        },
+        _comment: None,
+        default: None,
+        definitions: BTreeMap::new(),
+        _defs: BTreeMap::new(),
+        description: None,
+        examples: None,
+        read_only: None,
+        title: None,
+        write_only: None,
+        ..schema.clone()
+    } == Schema::default()
+}
+
+/// Merges the subschemas which two schemas have for the same keyword, such as the schemas of a
+/// property which both of them list.
+fn merge_subschema(result: &mut Schema, r: &Schema) -> Result<(), String> {
+    if *result == *r || is_false(result) {
+        return Ok(());
+    }
+    if is_unconstrained(r) {
+        override_option(&mut result.description, &r.description);
+        override_option(&mut result.title, &r.title);
+        override_option(&mut result.default, &r.default);
+        return Ok(());
+    }
+    if is_unconstrained(result) || is_false(r) {
+        let mut merged = r.clone();
+        fill_option(&mut merged.description, &result.description);
+        fill_option(&mut merged.title, &result.title);
+        fill_option(&mut merged.default, &result.default);
+        *result = merged;
+        return Ok(());
+    }
+    // References are resolved relative to the document they appear in, so these are only merged
+    // once the subschema is expanded
+    if result._ref.is_some() || r._ref.is_some() || result.all_of.is_some() || r.all_of.is_some() {
+        *result = Schema {
+            all_of: Some(vec![result.clone(), r.clone()]),
+            ..Schema::default()
+        };
+        return Ok(());
+    }
+    merge(result, r)
+}
+
+fn merge_subschemas(
+    result: &mut BTreeMap<String, Schema>,
+    r: &BTreeMap<String, Schema>,
+) -> Result<(), String> {
+    for (key, schema) in r {
+        match result.get_mut(key) {
+            Some(result) => {
+                merge_subschema(result, schema).map_err(|err| format!("{}: {}", key, err))?
+            }
+            None => {
+                result.insert(key.clone(), schema.clone());
+            }
+        }
+    }
+    Ok(())
+}
+
+fn merge_boxed(result: &mut Option<Box<Schema>>, r: &Option<Box<Schema>>) -> Result<(), String> {
+    match (result.as_mut(), r) {
+        (Some(result), Some(r)) => merge_subschema(result, r),
+        (None, Some(r)) => {
+            *result = Some(r.clone());
+            Ok(())
+        }
+        (_, None) => Ok(()),
+    }
+}
+
+fn merge_items(result: &mut Schema, r: &Schema) -> Result<(), String> {
+    let r_items = match r.items.as_deref() {
+        Some(items) => items,
+        None => return Ok(()),
+    };
+    let result_items = match result.items.as_deref_mut() {
+        Some(items) => items,
+        None => {
+            result.items = r.items.clone();
+            return Ok(());
+        }
+    };
+    match (result_items, r_items) {
+        (Items::Schema(result), Items::Schema(r)) => merge_subschema(result, r),
+        (Items::SchemaArray(result), Items::SchemaArray(r)) => merge_positions(result, r),
+        (Items::SchemaArray(result), Items::Schema(r)) => {
+            for item in result {
+                merge_subschema(item, r)?;
+            }
+            Ok(())
+        }
+        (Items::Schema(every), Items::SchemaArray(r)) => {
+            let items = r
+                .iter()
+                .map(|item| {
+                    let mut merged = every.clone();
+                    merge_subschema(&mut merged, item)?;
+                    Ok(merged)
+                })
+                .collect::<Result<_, String>>()?;
+            result.items = Some(Box::new(Items::SchemaArray(items)));
+            Ok(())
+        }
+    }
+}
+
+/// Merges the schemas of the items at the same positions.
+fn merge_positions(result: &mut Vec<Schema>, r: &[Schema]) -> Result<(), String> {
+    for (i, item) in r.iter().enumerate() {
+        match result.get_mut(i) {
+            Some(result) => {
+                merge_subschema(result, item).map_err(|err| format!("{}: {}", i, err))?
+            }
+            None => result.push(item.clone()),
+        }
+    }
+    Ok(())
+}
+
+fn merge_types(result: &mut Vec<SimpleTypes>, r: &[SimpleTypes]) -> Result<(), String> {
+    if r.is_empty() {
+        return Ok(());
+    }
+    if result.is_empty() {
+        *result = r.to_vec();
+        return Ok(());
+    }
+    // Integers are numbers as well
+    let allows = |types: &[SimpleTypes], typ: &SimpleTypes| {
+        types.contains(typ)
+            || (*typ == SimpleTypes::Integer && types.contains(&SimpleTypes::Number))
+    };
+    let mut types = Vec::new();
+    for typ in result.iter().chain(r) {
+        if allows(result, typ) && allows(r, typ) && !types.contains(typ) {
+            types.push(typ.clone());
+        }
+    }
+    if types.contains(&SimpleTypes::Number) {
+        types.retain(|typ| *typ != SimpleTypes::Integer);
+    }
+    if types.is_empty() {
+        return Err(format!(
+            "the types {:?} and {:?} have no value in common",
+            result, r
+        ));
+    }
+    *result = types;
+    Ok(())
+}
+
+fn merge_enum(result: &mut Schema, r: &Schema) -> Result<(), String> {
+    match (result.enum_.take(), &r.enum_) {
+        (Some(values), Some(r_values)) => {
+            let names = result
+                .enum_names
+                .take()
+                .filter(|names| names.len() == values.len());
+            let kept = values
+                .iter()
+                .map(|value| r_values.contains(value))
+                .collect::<Vec<_>>();
+            if !values.is_empty() && !r_values.is_empty() && !kept.contains(&true) {
+                return Err(format!(
+                    "the enums {} and {} have no value in common",
+                    Value::Array(values),
+                    Value::Array(r_values.clone())
+                ));
+            }
+            result.enum_names = names.map(|names| {
+                names
+                    .into_iter()
+                    .zip(&kept)
+                    .filter(|&(_, &kept)| kept)
+                    .map(|(name, _)| name)
+                    .collect()
+            });
+            result.enum_ = Some(
+                values
+                    .into_iter()
+                    .zip(&kept)
+                    .filter(|&(_, &kept)| kept)
+                    .map(|(value, _)| value)
+                    .collect(),
+            );
+        }
+        (values, _) if values.is_some() => result.enum_ = values,
+        (_, r_values) => {
+            result.enum_ = r_values.clone();
+            result.enum_names = r.enum_names.clone();
+        }
+    }
+
+    match (&result.const_, &r.const_) {
+        (Some(value), Some(r_value)) if value != r_value => {
+            return Err(format!("the constants {} and {} differ", value, r_value))
+        }
+        (None, Some(_)) => result.const_ = r.const_.clone(),
+        _ => (),
+    }
+    if let (Some(value), Some(values)) = (&result.const_, &result.enum_) {
+        if !values.is_empty() && !values.contains(value) {
+            return Err(format!(
+                "the constant {} is not one of {}",
+                value,
+                Value::Array(values.clone())
+            ));
+        }
+    }
+    Ok(())
+}
+
+fn merge_numbers(result: &mut Schema, r: &Schema) -> Result<(), String> {
+    // Draft 4 `exclusiveMinimum: true` and `exclusiveMaximum: true` are turned into the limits of
+    // later drafts so that limits of both kinds can be compared
+    let mut r = r.clone();
+    for schema in [&mut *result, &mut r] {
+        if let Some(ExclusiveLimit::Boolean(exclusive)) = schema.exclusive_minimum {
+            schema.exclusive_minimum = match exclusive {
+                true => schema.minimum.take().map(ExclusiveLimit::Number),
+                false => None,
+            };
+        }
+        if let Some(ExclusiveLimit::Boolean(exclusive)) = schema.exclusive_maximum {
+            schema.exclusive_maximum = match exclusive {
+                true => schema.maximum.take().map(ExclusiveLimit::Number),
+                false => None,
+            };
+        }
+    }
+    let limit = |limit: &Option<ExclusiveLimit>| match *limit {
+        Some(ExclusiveLimit::Number(limit)) => Some(limit),
+        _ => None,
+    };
+    let minimum = merge_with(result.minimum, r.minimum, f64::max);
+    let maximum = merge_with(result.maximum, r.maximum, f64::min);
+    let exclusive_minimum = merge_with(
+        limit(&result.exclusive_minimum),
+        limit(&r.exclusive_minimum),
+        f64::max,
+    );
+    let exclusive_maximum = merge_with(
+        limit(&result.exclusive_maximum),
+        limit(&r.exclusive_maximum),
+        f64::min,
+    );
+    let empty = |lower: Option<f64>, upper: Option<f64>, inclusive: bool| match (lower, upper) {
+        (Some(lower), Some(upper)) => lower > upper || (!inclusive && lower == upper),
+        _ => false,
+    };
+    if empty(minimum, maximum, true)
+        || empty(minimum, exclusive_maximum, false)
+        || empty(exclusive_minimum, maximum, false)
+        || empty(exclusive_minimum, exclusive_maximum, false)
+    {
+        return Err("no number lies within the limits".into());
+    }
+    result.minimum = minimum;
+    result.maximum = maximum;
+    result.exclusive_minimum = exclusive_minimum.map(ExclusiveLimit::Number);
+    result.exclusive_maximum = exclusive_maximum.map(ExclusiveLimit::Number);
+
+    result.multiple_of = merge_with(result.multiple_of, r.multiple_of, |a, b| {
+        if (a / b).fract() == 0.0 {
+            a
+        } else if (b / a).fract() == 0.0 {
+            b
+        } else if a.fract() == 0.0 && b.fract() == 0.0 {
+            a / gcd(a as u64, b as u64) as f64 * b
+        } else {
+            a
+        }
+    });
+    Ok(())
+}
+
+fn gcd(a: u64, b: u64) -> u64 {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+/// Merges the bounds of the length of strings, arrays or objects.
+fn merge_lengths(
+    what: &str,
+    min: &mut Option<i64>,
+    max: &mut Option<i64>,
+    r_min: Option<i64>,
+    r_max: Option<i64>,
+) -> Result<(), String> {
+    *min = merge_with(*min, r_min, i64::max);
+    *max = merge_with(*max, r_max, i64::min);
+    match (*min, *max) {
+        (Some(min), Some(max)) if min > max => Err(format!(
+            "the {} must be at least {} and at most {}",
+            what, min, max
+        )),
+        _ => Ok(()),
+    }
+}
+
+fn merge_with<T>(result: Option<T>, r: Option<T>, f: impl FnOnce(T, T) -> T) -> Option<T> {
+    match (result, r) {
+        (Some(result), Some(r)) => Some(f(result, r)),
+        (result, r) => result.or(r),
+    }
+}
+
+fn merge_flag(result: &mut Option<bool>, r: Option<bool>) {
+    *result = merge_with(*result, r, |result, r| result || r);
+}
+
+fn override_option<T: Clone>(result: &mut Option<T>, r: &Option<T>) {
+    if r.is_some() {
+        *result = r.clone();
+    }
+}
+
+fn fill_option<T: Clone>(result: &mut Option<T>, r: &Option<T>) {
+    if result.is_none() {
+        *result = r.clone();
+    }
+}
+
+/// Takes the keyword of `r` if `result` lacks it, or keeps it in `rest` if both differ.
+fn keep_both<T: Clone + PartialEq>(result: &mut Option<T>, r: &Option<T>, rest: &mut Option<T>) {
+    match (&*result, r) {
+        (None, _) => *result = r.clone(),
+        (Some(value), Some(r_value)) if value != r_value => *rest = r.clone(),
+        _ => (),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn schema(json: &str) -> Schema {
+        json.parse().unwrap()
+    }
+
+    fn merged(a: &str, b: &str) -> Result<Schema, String> {
+        let mut result = schema(a);
+        merge(&mut result, &schema(b)).map(|()| result)
+    }
+
+    #[test]
+    fn types() {
+        assert_eq!(
+            merged(r#"{"type": "number"}"#, r#"{"type": "integer"}"#)
+                .unwrap()
+                .type_,
+            [SimpleTypes::Integer]
+        );
+        assert_eq!(
+            merged(r#"{"type": ["string", "null"]}"#, r#"{"type": "string"}"#)
+                .unwrap()
+                .type_,
+            [SimpleTypes::String]
+        );
+        assert_eq!(
+            merged(r#"{"type": "integer"}"#, r#"{"default": 0}"#)
+                .unwrap()
+                .type_,
+            [SimpleTypes::Integer]
+        );
+        assert!(merged(r#"{"type": "string"}"#, r#"{"type": "integer"}"#).is_err());
+    }
+
+    #[test]
+    fn limits() {
+        let result = merged(
+            r#"{"minimum": 0, "maximum": 10, "minLength": 2}"#,
+            r#"{"minimum": 1, "exclusiveMaximum": 5, "maxLength": 4}"#,
+        )
+        .unwrap();
+        assert_eq!((result.minimum, result.maximum), (Some(1.0), Some(10.0)));
+        assert_eq!(result.exclusive_maximum, Some(ExclusiveLimit::Number(5.0)));
+        assert_eq!((result.min_length, result.max_length), (Some(2), Some(4)));
+
+        // Draft 4 exclusive limits
+        let result = merged(
+            r#"{"minimum": 0, "exclusiveMinimum": true}"#,
+            r#"{"minimum": -1}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            (result.minimum, result.exclusive_minimum),
+            (Some(-1.0), Some(ExclusiveLimit::Number(0.0)))
+        );
+
+        assert_eq!(
+            merged(r#"{"multipleOf": 4}"#, r#"{"multipleOf": 6}"#)
+                .unwrap()
+                .multiple_of,
+            Some(12.0)
+        );
+        assert!(merged(r#"{"minimum": 2}"#, r#"{"maximum": 1}"#).is_err());
+        assert!(merged(r#"{"minimum": 1}"#, r#"{"exclusiveMaximum": 1}"#).is_err());
+        assert!(merged(r#"{"minItems": 3}"#, r#"{"maxItems": 2}"#).is_err());
+    }
+
+    #[test]
+    fn enums() {
+        let result = merged(
+            r#"{"enum": ["a", "b", "c"], "enumNames": ["A", "B", "C"]}"#,
+            r#"{"enum": ["c", "b", "d"]}"#,
+        )
+        .unwrap();
+        assert_eq!(result.enum_, Some(vec!["b".into(), "c".into()]));
+        assert_eq!(result.enum_names, Some(vec!["B".into(), "C".into()]));
+        assert!(merged(r#"{"enum": ["a"]}"#, r#"{"enum": ["b"]}"#).is_err());
+        assert!(merged(r#"{"const": "a"}"#, r#"{"const": "b"}"#).is_err());
+        assert!(merged(r#"{"enum": ["a"]}"#, r#"{"const": "b"}"#).is_err());
+    }
+
+    #[test]
+    fn objects() {
+        let result = merged(
+            r##"{
+                "properties": { "a": { "type": "string" }, "b": { "$ref": "#/b" } },
+                "required": ["a"],
+                "additionalProperties": { "type": "integer" }
+            }"##,
+            r#"{
+                "properties": { "a": { "maxLength": 3 }, "b": { "description": "B" }, "c": {} },
+                "required": ["a", "c"],
+                "additionalProperties": false
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(
+            result.properties["a"],
+            schema(r#"{"type": "string", "maxLength": 3}"#)
+        );
+        assert_eq!(
+            result.properties["b"],
+            schema(r##"{"$ref": "#/b", "description": "B"}"##)
+        );
+        // Only allowed by the `additionalProperties` of the first schema
+        assert_eq!(result.properties["c"], schema(r#"{"type": "integer"}"#));
+        assert_eq!(result.required, Some(vec!["a".into(), "c".into()]));
+        assert!(is_false(result.additional_properties.as_ref().unwrap()));
+
+        let result = merged(
+            r#"{
+                "properties": { "a": { "type": "string" } },
+                "patternProperties": { "^x-": { "type": "string" } },
+                "additionalProperties": false
+            }"#,
+            r#"{
+                "properties": { "b": { "type": "integer" }, "x-b": { "minLength": 1 } },
+                "patternProperties": { "^y-": { "type": "integer" } }
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(
+            result.properties.keys().collect::<Vec<_>>(),
+            ["a", "x-b"],
+            "`b` is never allowed"
+        );
+        assert_eq!(result.properties["x-b"], schema(r#"{"minLength": 1}"#));
+        assert!(is_false(&result.pattern_properties["^y-"]));
+
+        let result = merged(
+            r##"{"properties": { "a": { "$ref": "#/a" } }}"##,
+            r#"{"properties": { "a": { "type": "object" } }}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            result.properties["a"],
+            schema(r##"{"allOf": [{ "$ref": "#/a" }, { "type": "object" }]}"##)
+        );
+        assert!(merged(
+            r#"{"properties": { "a": { "type": "string" } }}"#,
+            r#"{"properties": { "a": { "type": "integer" } }}"#,
+        )
+        .is_err());
+        assert!(merged(
+            r#"{"properties": { "a": {} }, "additionalProperties": false}"#,
+            r#"{"properties": { "b": {} }, "required": ["b"]}"#,
+        )
+        .is_err());
+        assert!(merged(
+            r#"{"properties": { "a": {} }, "additionalProperties": false}"#,
+            r#"{"required": ["b"]}"#,
+        )
+        .is_err());
+        assert!(merged(
+            r#"{"properties": { "a": {} }, "additionalProperties": { "type": "string" }}"#,
+            r#"{"properties": { "b": { "type": "integer" } }, "required": ["b"]}"#,
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn leftovers() {
+        let result = merged(r#"{"pattern": "^a"}"#, r#"{"pattern": "b$"}"#).unwrap();
+        assert_eq!(result.pattern.as_deref(), Some("^a"));
+        assert_eq!(result.all_of, Some(vec![schema(r#"{"pattern": "b$"}"#)]));
+
+        let result = merged(r#"{"pattern": "^a"}"#, r#"{"pattern": "^a"}"#).unwrap();
+        assert_eq!(result.all_of, None);
+
+        let result = merged(
+            r#"{"oneOf": [{ "type": "string" }, { "type": "integer" }]}"#,
+            r#"{"oneOf": [{ "minimum": 1 }, { "maxLength": 2 }]}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            result.one_of,
+            Some(vec![
+                schema(r#"{"type": "string"}"#),
+                schema(r#"{"type": "integer"}"#)
+            ])
+        );
+        assert_eq!(
+            result.all_of,
+            Some(vec![schema(
+                r#"{"oneOf": [{ "minimum": 1 }, { "maxLength": 2 }]}"#
+            )])
+        );
+
+        // Parts left over by earlier merges are kept as well
+        let mut result = schema(r#"{"contains": { "type": "string" }}"#);
+        let r = merged(
+            r#"{"if": { "type": "string" }, "then": { "minLength": 1 }}"#,
+            r#"{"if": { "type": "number" }, "then": { "minimum": 1 }}"#,
+        )
+        .unwrap();
+        merge(&mut result, &r).unwrap();
+        merge(
+            &mut result,
+            &schema(r#"{"contains": { "type": "integer" }}"#),
+        )
+        .unwrap();
+        assert_eq!(
+            result.all_of,
+            Some(vec![
+                schema(r#"{"if": { "type": "number" }, "then": { "minimum": 1 }}"#),
+                schema(r#"{"contains": { "type": "integer" }}"#)
+            ])
+        );
+    }
+
+    #[test]
+    fn items() {
+        let result = merged(
+            r#"{"items": [{ "type": "string" }, {}]}"#,
+            r#"{"items": { "minLength": 1 }}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            result.positional_items().unwrap(),
+            [
+                schema(r#"{"type": "string", "minLength": 1}"#),
+                schema(r#"{"minLength": 1}"#)
+            ]
+        );
+    }
+}
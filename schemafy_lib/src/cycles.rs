@@ -0,0 +1,264 @@
+//! Detection of types which hold each other by value.
+//!
+//! A struct or enum which contains itself, directly or through other types, has an infinite
+//! size. The expander records which type holds which and boxes one reference of every cycle.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::ops::Range;
+
+/// The generic types which keep their parameters on the heap.
+const INDIRECTIONS: &[&str] = &[
+    "Arc",
+    "BTreeMap",
+    "BTreeSet",
+    "Box",
+    "HashMap",
+    "HashSet",
+    "OneOrMany",
+    "Rc",
+    "Vec",
+    "VecDeque",
+];
+
+/// Returns the positions of the type names in `typ` which are held by value, that is which are
+/// not parameters of a type in `INDIRECTIONS` such as `Vec`.
+///
+/// Paths such as `serde_json::Value` are left out as they never name a generated type.
+pub(crate) fn held_types(typ: &str) -> Vec<Range<usize>> {
+    let mut held = Vec::new();
+    // Whether each enclosing `<`, `(` or `[` is within an indirection
+    let mut indirect = vec![false];
+    let mut chars = typ.char_indices().peekable();
+    while let Some((start, c)) = chars.next() {
+        let within = *indirect.last().unwrap();
+        if is_path_char(c) {
+            let mut end = start + c.len_utf8();
+            while let Some(&(i, c)) = chars.peek() {
+                if !is_path_char(c) {
+                    break;
+                }
+                end = i + c.len_utf8();
+                chars.next();
+            }
+            while matches!(chars.peek(), Some(&(_, c)) if c.is_whitespace()) {
+                chars.next();
+            }
+            let path = &typ[start..end];
+            let name = path.rsplit("::").next().unwrap_or(path);
+            if chars.peek().map(|&(_, c)| c) == Some('<') {
+                chars.next();
+                indirect.push(within || INDIRECTIONS.contains(&name));
+            } else if !within && !path.contains("::") && name.starts_with(char::is_uppercase) {
+                held.push(start..end);
+            }
+        } else {
+            match c {
+                '(' | '[' => indirect.push(within),
+                '>' | ')' | ']' if indirect.len() > 1 => {
+                    indirect.pop();
+                }
+                _ => (),
+            }
+        }
+    }
+    held
+}
+
+fn is_path_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == ':'
+}
+
+/// Returns the pairs of `edges` (a type and a type it holds by value) which must be boxed so
+/// that no type holds itself.
+///
+/// The strongly connected components of the graph are searched depth first, starting from the
+/// types in the order they were recorded, and every edge leading back to a type on the current
+/// path is boxed. Edges between components never close a cycle and are left as they are.
+pub(crate) fn recursive_edges(edges: &[(String, String)]) -> BTreeSet<(String, String)> {
+    let mut nodes = Vec::new();
+    let mut indices = BTreeMap::new();
+    for (from, to) in edges {
+        for node in &[from, to] {
+            if !indices.contains_key(*node) {
+                indices.insert((*node).clone(), nodes.len());
+                nodes.push((*node).clone());
+            }
+        }
+    }
+    let mut successors = vec![Vec::new(); nodes.len()];
+    for (from, to) in edges {
+        let (from, to) = (indices[from], indices[to]);
+        if !successors[from].contains(&to) {
+            successors[from].push(to);
+        }
+    }
+
+    let components = components(&successors);
+    let mut search = Search {
+        successors: &successors,
+        components: &components,
+        visited: vec![false; nodes.len()],
+        on_path: vec![false; nodes.len()],
+        back_edges: Vec::new(),
+    };
+    for node in 0..nodes.len() {
+        if !search.visited[node] {
+            search.visit(node);
+        }
+    }
+    search
+        .back_edges
+        .into_iter()
+        .map(|(from, to)| (nodes[from].clone(), nodes[to].clone()))
+        .collect()
+}
+
+struct Search<'a> {
+    successors: &'a [Vec<usize>],
+    components: &'a [usize],
+    visited: Vec<bool>,
+    on_path: Vec<bool>,
+    back_edges: Vec<(usize, usize)>,
+}
+
+impl Search<'_> {
+    fn visit(&mut self, node: usize) {
+        self.visited[node] = true;
+        self.on_path[node] = true;
+        for &next in &self.successors[node] {
+            if self.components[next] != self.components[node] {
+                continue;
+            }
+            if self.on_path[next] {
+                self.back_edges.push((node, next));
+            } else if !self.visited[next] {
+                self.visit(next);
+            }
+        }
+        self.on_path[node] = false;
+    }
+}
+
+/// Returns the strongly connected component of each node, using Tarjan's algorithm.
+fn components(successors: &[Vec<usize>]) -> Vec<usize> {
+    let mut tarjan = Tarjan {
+        successors,
+        index: vec![None; successors.len()],
+        lowlink: vec![0; successors.len()],
+        on_stack: vec![false; successors.len()],
+        stack: Vec::new(),
+        next_index: 0,
+        components: vec![0; successors.len()],
+        count: 0,
+    };
+    for node in 0..successors.len() {
+        if tarjan.index[node].is_none() {
+            tarjan.connect(node);
+        }
+    }
+    tarjan.components
+}
+
+struct Tarjan<'a> {
+    successors: &'a [Vec<usize>],
+    index: Vec<Option<usize>>,
+    lowlink: Vec<usize>,
+    on_stack: Vec<bool>,
+    stack: Vec<usize>,
+    next_index: usize,
+    components: Vec<usize>,
+    count: usize,
+}
+
+impl Tarjan<'_> {
+    fn connect(&mut self, node: usize) {
+        self.index[node] = Some(self.next_index);
+        self.lowlink[node] = self.next_index;
+        self.next_index += 1;
+        self.stack.push(node);
+        self.on_stack[node] = true;
+
+        for &next in &self.successors[node] {
+            match self.index[next] {
+                None => {
+                    self.connect(next);
+                    self.lowlink[node] = self.lowlink[node].min(self.lowlink[next]);
+                }
+                Some(index) if self.on_stack[next] => {
+                    self.lowlink[node] = self.lowlink[node].min(index);
+                }
+                Some(_) => (),
+            }
+        }
+
+        if Some(self.lowlink[node]) == self.index[node] {
+            loop {
+                let member = self.stack.pop().unwrap();
+                self.on_stack[member] = false;
+                self.components[member] = self.count;
+                if member == node {
+                    break;
+                }
+            }
+            self.count += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn held(typ: &str) -> Vec<&str> {
+        held_types(typ)
+            .into_iter()
+            .map(|range| &typ[range])
+            .collect()
+    }
+
+    fn edges(edges: &[(&str, &str)]) -> BTreeSet<(String, String)> {
+        let edges = edges
+            .iter()
+            .map(|&(from, to)| (from.to_string(), to.to_string()))
+            .collect::<Vec<_>>();
+        recursive_edges(&edges)
+    }
+
+    #[test]
+    fn held_by_value() {
+        assert_eq!(held("Node"), vec!["Node"]);
+        assert_eq!(held("Option<Node>"), vec!["Node"]);
+        assert_eq!(held("(Node, [Leaf; 2])"), vec!["Node", "Leaf"]);
+        assert_eq!(held("Vec<Node>"), Vec::<&str>::new());
+        assert_eq!(held("Option<Vec<Node>>"), Vec::<&str>::new());
+        assert_eq!(held("BTreeMap<Key, Option<Node>>"), Vec::<&str>::new());
+        assert_eq!(
+            held("::schemafy_core::one_or_many::OneOrMany<Node>"),
+            Vec::<&str>::new()
+        );
+        assert_eq!(held("Option<serde_json::Value>"), Vec::<&str>::new());
+        assert_eq!(held("(Vec<Node>, Leaf)"), vec!["Leaf"]);
+        assert_eq!(held("i64"), Vec::<&str>::new());
+    }
+
+    #[test]
+    fn boxes_one_edge_per_cycle() {
+        let boxed = edges(&[("A", "A")]);
+        assert_eq!(boxed.len(), 1);
+        assert!(boxed.contains(&("A".to_string(), "A".to_string())));
+
+        let boxed = edges(&[("A", "B"), ("B", "A"), ("B", "C"), ("C", "String")]);
+        assert_eq!(boxed.len(), 1);
+        assert!(boxed.contains(&("B".to_string(), "A".to_string())));
+
+        let boxed = edges(&[("A", "B"), ("B", "C"), ("C", "A"), ("C", "B")]);
+        assert_eq!(boxed.len(), 2);
+        assert!(boxed.contains(&("C".to_string(), "A".to_string())));
+        assert!(boxed.contains(&("C".to_string(), "B".to_string())));
+    }
+
+    #[test]
+    fn leaves_acyclic_graphs() {
+        assert!(edges(&[("A", "B"), ("A", "C"), ("B", "C"), ("C", "D")]).is_empty());
+    }
+}
@@ -0,0 +1,48 @@
+use std::fmt;
+
+/// An error which prevents types from being generated from a schema.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Error {
+    document: Option<String>,
+    pointer: String,
+    reason: String,
+}
+
+impl Error {
+    pub(crate) fn new(document: Option<String>, pointer: String, reason: String) -> Error {
+        Error {
+            document,
+            pointer,
+            reason,
+        }
+    }
+
+    /// The document containing the offending schema, or `None` if it is the root document.
+    pub fn document(&self) -> Option<&str> {
+        self.document.as_deref()
+    }
+
+    /// The JSON pointer of the offending schema within its document.
+    pub fn pointer(&self) -> &str {
+        &self.pointer
+    }
+
+    /// Why no types could be generated.
+    pub fn reason(&self) -> &str {
+        &self.reason
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} (at `{}#{}`)",
+            self.reason,
+            self.document.as_deref().unwrap_or(""),
+            self.pointer
+        )
+    }
+}
+
+impl std::error::Error for Error {}
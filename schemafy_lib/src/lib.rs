@@ -4,10 +4,12 @@
 // #![doc(include = "../README.md")]
 
 //! This is a Rust crate which can take a [json schema (draft
-//! 4)](http://json-schema.org/) and generate Rust types which are
-//! serializable with [serde](https://serde.rs/). No checking such as
-//! `min_value` are done but instead only the structure of the schema
-//! is followed as closely as possible.
+//! 4, 6, 7, 2019-09 or 2020-12)](http://json-schema.org/) and generate Rust types which are
+//! serializable with [serde](https://serde.rs/). By default the types
+//! only follow the structure of the schema as closely as possible.
+//! Checking constraints such as `minimum` is opt-in, through a generated
+//! `validate` method (see [`Expander::with_validation`]), when
+//! deserializing or through newtypes for constrained definitions.
 //!
 //! As a schema could be arbitrarily complex this crate makes no
 //! guarantee that it can generate good types or even any types at all
@@ -36,14 +38,32 @@
 //!
 //! let json = std::fs::read_to_string("src/schema.json").expect("Read schema JSON file");
 //!
-//! let schema = serde_json::from_str(&json).unwrap();
+//! let schema = json.parse().unwrap();
 //! let mut expander = Expander::new(
 //!     Some("Schema"),
 //!     "::schemafy_core::",
 //!     &schema,
 //! );
 //!
-//! let code = expander.expand(&schema);
+//! let code = expander.expand(&schema).unwrap();
+//! ```
+//!
+//! `$ref`s into other files (such as `common.json#/definitions/Id`) are resolved relative to
+//! the referring schema by a [`SchemaStore`](./struct.SchemaStore.html) which loads them up
+//! front. Documents are read from the filesystem unless another
+//! [`SchemaResolver`](./trait.SchemaResolver.html) is passed to `SchemaStore::load_with`.
+//!
+//! ```rust
+//! use schemafy_lib::{Expander, SchemaStore};
+//!
+//! let json = std::fs::read_to_string("src/schema.json").expect("Read schema JSON file");
+//!
+//! let schema = json.parse().unwrap();
+//! let store = SchemaStore::load("src/schema.json", &schema).unwrap();
+//! let mut expander = Expander::new(Some("Schema"), "::schemafy_core::", &schema)
+//!     .with_store(&store);
+//!
+//! let code = expander.expand_root().unwrap();
 //! ```
 
 #[macro_use]
@@ -52,18 +72,37 @@ extern crate serde_derive;
 #[macro_use]
 extern crate quote;
 
-/// Types from the JSON Schema meta-schema (draft 4).
+mod all_of;
+mod cycles;
+mod error;
+mod pointer;
+mod resolver;
+/// Types of the keywords of every supported draft.
 ///
-/// This module is itself generated from a JSON schema.
+/// This module is itself generated from `schema.json` with `cargo build --features
+/// internal-regenerate` and must not be edited by hand, see `schema_ext` instead.
+#[allow(clippy::large_enum_variant)]
 mod schema;
+mod schema_ext;
+mod store;
 
-use std::borrow::Cow;
+use std::{
+    borrow::Cow,
+    collections::{btree_map::Entry, BTreeMap, BTreeSet},
+    convert::TryFrom,
+    path::Path,
+};
 
 use inflector::Inflector;
 
 use serde_json::Value;
 
-pub use schema::{Schema, SimpleTypes};
+pub use error::Error;
+pub use schema::{ExclusiveLimit, Items, Schema, SchemaDependencies, SimpleTypes};
+pub use resolver::{DirectoryResolver, FileResolver, MemoryResolver, SchemaResolver};
+pub use store::SchemaStore;
+
+pub use schemafy_core::validator::{SchemaError, Validator};
 
 use proc_macro2::{Span, TokenStream};
 
@@ -79,6 +118,13 @@ fn replace_numeric_start(s: &str) -> String {
     }
 }
 
+/// Returns the name of the type (or enum variant) generated for a definition (or value) called
+/// `s`.
+fn type_name(s: &str) -> String {
+    let s = s.to_pascal_case();
+    str_to_ident(if s.is_empty() { "Empty" } else { &s }).to_string()
+}
+
 pub fn str_to_ident(s: &str) -> syn::Ident {
     let s = replace_invalid_identifier_chars(s);
     let s = replace_numeric_start(&s);
@@ -87,7 +133,7 @@ pub fn str_to_ident(s: &str) -> syn::Ident {
         "for", "if", "impl", "in", "let", "loop", "match", "mod", "move", "mut", "pub", "ref",
         "return", "self", "static", "struct", "super", "trait", "true", "type", "unsafe", "use",
         "where", "while", "abstract", "become", "box", "do", "final", "macro", "override", "priv",
-        "typeof", "unsized", "virtual", "yield", "async", "await", "try",
+        "typeof", "unsized", "virtual", "yield", "async", "await", "try", "Self",
     ];
 
     if keywords.iter().any(|&keyword| keyword == s) {
@@ -97,80 +143,167 @@ pub fn str_to_ident(s: &str) -> syn::Ident {
     }
 }
 
-fn rename_keyword(prefix: &str, s: &str) -> Option<TokenStream> {
-    let n = str_to_ident(s);
+fn field(s: &str) -> TokenStream {
+    let field = field_ident(s);
+    if field == s {
+        quote!( pub #field )
+    } else {
+        quote! {
+            #[serde(rename = #s)]
+            pub #field
+        }
+    }
+}
 
+fn field_ident(s: &str) -> syn::Ident {
+    let n = str_to_ident(s);
     if n != s {
-        let prefix = syn::Ident::new(prefix, Span::call_site());
-        Some(quote! {
-            #[serde(rename = #s)]
-            #prefix #n
-        })
+        return n;
+    }
+    let snake = s.to_snake_case();
+    if snake == "ref" {
+        syn::Ident::new("ref_", Span::call_site())
+    } else if snake != s || snake.contains(['$', '#']) {
+        syn::Ident::new(&snake.replace(['$', '#'], ""), Span::call_site())
     } else {
-        None
+        syn::Ident::new(s, Span::call_site())
     }
 }
 
-fn field(s: &str) -> TokenStream {
-    if let Some(t) = rename_keyword("pub", s) {
-        t
-    } else {
-        let snake = s.to_snake_case();
-        if snake != s || snake.contains(|c: char| c == '$' || c == '#') {
-            let field = if snake == "ref" {
-                syn::Ident::new("ref_".into(), Span::call_site())
-            } else {
-                syn::Ident::new(&snake.replace('$', "").replace('#', ""), Span::call_site())
-            };
+/// Returns the integer type named by an (OpenAPI style) `format` of an integer schema.
+fn integer_format(format: &str) -> Option<&'static str> {
+    Some(match format {
+        "int8" => "i8",
+        "int16" => "i16",
+        "int32" => "i32",
+        "int64" => "i64",
+        "uint8" => "u8",
+        "uint16" => "u16",
+        "uint32" => "u32",
+        "uint64" => "u64",
+        _ => return None,
+    })
+}
 
-            quote! {
-                #[serde(rename = #s)]
-                pub #field
-            }
-        } else {
-            let field = syn::Ident::new(s, Span::call_site());
-            quote!( pub #field )
+/// Returns the smallest integer type which holds every integer from `lower` to `upper`. Integers
+/// without a bound are assumed to fit in 64 bits.
+fn fitting_integer(lower: Option<f64>, upper: Option<f64>) -> &'static str {
+    match lower {
+        Some(lower) if lower >= 0.0 => {
+            let upper = upper.unwrap_or(u64::MAX as f64);
+            [
+                ("u8", u8::MAX as f64),
+                ("u16", u16::MAX as f64),
+                ("u32", u32::MAX as f64),
+                ("u64", u64::MAX as f64),
+            ]
+            .iter()
+            .find(|&&(_, max)| upper <= max)
+            .map_or("u128", |&(typ, _)| typ)
+        }
+        _ => {
+            let lower = lower.unwrap_or(i64::MIN as f64);
+            let upper = upper.unwrap_or(i64::MAX as f64);
+            [
+                ("i8", i8::MIN as f64, i8::MAX as f64),
+                ("i16", i16::MIN as f64, i16::MAX as f64),
+                ("i32", i32::MIN as f64, i32::MAX as f64),
+                ("i64", i64::MIN as f64, i64::MAX as f64),
+            ]
+            .iter()
+            .find(|&&(_, min, max)| min <= lower && upper <= max)
+            .map_or("i128", |&(typ, _, _)| typ)
         }
     }
 }
 
-fn merge_option<T, F>(mut result: &mut Option<T>, r: &Option<T>, f: F)
-where
-    F: FnOnce(&mut T, &T),
-    T: Clone,
-{
-    *result = match (&mut result, r) {
-        (&mut &mut Some(ref mut result), &Some(ref r)) => return f(result, r),
-        (&mut &mut None, &Some(ref r)) => Some(r.clone()),
-        _ => return (),
-    };
+/// Returns the length of an array with `minItems == maxItems`, if it is one which serde can
+/// (de)serialize as a Rust array.
+fn fixed_length(schema: &Schema) -> Option<usize> {
+    let min_items = u64::try_from(schema.min_items?).ok()?;
+    match schema.max_items {
+        Some(max_items) if max_items as u64 == min_items && (1..=32).contains(&min_items) => {
+            Some(min_items as usize)
+        }
+        _ => None,
+    }
 }
 
-fn merge_all_of(result: &mut Schema, r: &Schema) {
-    use std::collections::btree_map::Entry;
-
-    for (k, v) in &r.properties {
-        match result.properties.entry(k.clone()) {
-            Entry::Vacant(entry) => {
-                entry.insert(v.clone());
-            }
-            Entry::Occupied(mut entry) => merge_all_of(entry.get_mut(), v),
+/// Returns the reference of an `allOf` which only adds annotations such as a `description` to it.
+fn annotated_ref(schema: &Schema) -> Option<&str> {
+    let all_of = schema.all_of.as_ref()?;
+    let mut constrained = all_of.iter().filter(|part| !all_of::is_unconstrained(part));
+    match (constrained.next(), constrained.next()) {
+        (Some(part), None)
+            if all_of::is_unconstrained(&Schema {
+                all_of: None,
+                ..schema.clone()
+            }) =>
+        {
+            part._ref.as_deref()
         }
+        _ => None,
     }
+}
 
-    if let Some(ref ref_) = r.ref_ {
-        result.ref_ = Some(ref_.clone());
+/// Returns the alternatives of a `oneOf` (or `anyOf`) which can be expanded into an enum.
+///
+/// Alternatives which carry no type information (such as `oneOf: [{ required: [..] }, ..]`)
+/// only constrain the enclosing schema so they are ignored.
+fn union_alternatives(schema: &Schema) -> Option<(&'static str, &[Schema])> {
+    let (keyword, alternatives) = match (&schema.one_of, &schema.any_of) {
+        (Some(one_of), _) if !one_of.is_empty() => ("oneOf", one_of),
+        (_, Some(any_of)) => ("anyOf", any_of),
+        _ => return None,
+    };
+    if alternatives.iter().any(has_type_information) {
+        Some((keyword, alternatives))
+    } else {
+        None
     }
+}
 
-    if let Some(ref description) = r.description {
-        result.description = Some(description.clone());
-    }
+fn has_type_information(schema: &Schema) -> bool {
+    schema._ref.is_some()
+        || !schema.type_.is_empty()
+        || !schema.properties.is_empty()
+        || schema.enum_.is_some()
+        || schema.all_of.is_some()
+        || schema.any_of.is_some()
+        || schema.one_of.is_some()
+}
 
-    merge_option(&mut result.required, &r.required, |required, r_required| {
-        required.extend(r_required.iter().cloned());
-    });
+/// Returns the name of a location-independent reference such as `#foo` given its fragment.
+fn anchor(fragment: &str) -> Option<&str> {
+    Some(fragment).filter(|anchor| !anchor.is_empty() && !anchor.starts_with('/'))
+}
 
-    result.type_.retain(|e| r.type_.contains(e));
+/// Returns true if the object described by `schema` may not contain properties besides the ones
+/// it lists. Objects with `patternProperties` are maps rather than structs.
+fn is_closed(schema: &Schema) -> bool {
+    schema.pattern_properties.is_empty()
+        && (matches!(schema.additional_properties.as_deref(), Some(s) if is_false(s))
+            || matches!(schema.unevaluated_properties.as_deref(), Some(s) if is_false(s)))
+}
+
+/// Returns true for the `patternProperties` patterns which match every property name.
+fn matches_every_name(pattern: &str) -> bool {
+    matches!(pattern, "" | ".*" | "^.*" | "^.*$")
+}
+
+/// Returns true for the `false` schema which does not accept any value.
+fn is_false(schema: &Schema) -> bool {
+    schema.not.as_deref() == Some(&Schema::default())
+}
+
+/// Removes any `{ type: null }` alternative, returning whether one was found. The remaining
+/// alternatives are paired with their index.
+fn split_null_alternative(alternatives: &[Schema]) -> (Vec<(usize, &Schema)>, bool) {
+    let (nulls, alternatives): (Vec<_>, Vec<_>) = alternatives
+        .iter()
+        .enumerate()
+        .partition(|(_, alternative)| alternative.type_ == [SimpleTypes::Null]);
+    (alternatives, !nulls.is_empty())
 }
 
 const LINE_LENGTH: usize = 100;
@@ -215,16 +348,32 @@ fn make_doc_comment(mut comment: &str, remaining_line: usize) -> TokenStream {
 
 struct FieldExpander<'a, 'r: 'a> {
     default: bool,
+    /// The statements which validate each field, if validation is enabled
+    validations: Vec<TokenStream>,
+    /// The identifiers of the fields
+    idents: Vec<syn::Ident>,
     expander: &'a mut Expander<'r>,
 }
 
 impl<'a, 'r> FieldExpander<'a, 'r> {
-    fn expand_fields(&mut self, type_name: &str, schema: &Schema) -> Vec<TokenStream> {
-        let schema = self.expander.schema(schema);
-        schema
+    fn expand_fields(&mut self, type_name: &str, schema: &Schema) -> Result<Vec<TokenStream>, Error> {
+        let schema = self.expander.schema(schema)?;
+        let mut properties = schema
             .properties
             .iter()
-            .map(|(field_name, value)| {
+            .map(|(field_name, value)| (field_name, (value, vec!["properties"])))
+            .collect::<BTreeMap<_, _>>();
+        // Properties which only appear in `dependentSchemas` are optional fields
+        for (key, dependent) in &schema.dependent_schemas {
+            for (field_name, value) in &dependent.properties {
+                properties
+                    .entry(field_name)
+                    .or_insert((value, vec!["dependentSchemas", key, "properties"]));
+            }
+        }
+        let mut fields = properties
+            .into_iter()
+            .map(|(field_name, (value, mut location))| {
                 self.expander.current_field.clone_from(field_name);
                 let key = field(field_name);
                 let required = schema
@@ -232,7 +381,17 @@ impl<'a, 'r> FieldExpander<'a, 'r> {
                     .iter()
                     .flat_map(|a| a.iter())
                     .any(|req| req == field_name);
-                let field_type = self.expander.expand_type(type_name, required, value);
+                location.push(field_name);
+                let field_type = match self
+                    .expander
+                    .time_field_type(value, required)
+                    .or_else(|| self.expander.decimal_field_type(value, required))
+                {
+                    Some(field_type) => field_type,
+                    None => self.expander.at(&location, |expander| {
+                        expander.expand_type(type_name, required, value)
+                    })?,
+                };
                 if !field_type.typ.starts_with("Option<") {
                     self.default = false;
                 }
@@ -254,28 +413,186 @@ impl<'a, 'r> FieldExpander<'a, 'r> {
                         #[serde( #(#attributes),* )]
                     })
                 };
+                if self.expander.validate {
+                    let validation = self
+                        .expander
+                        .at(&location, |expander| expander.validate_field(field_name, value))?;
+                    self.validations.push(validation);
+                }
+                self.idents.push(field_ident(field_name));
                 let comment = value
                     .description
                     .as_ref()
                     .map(|comment| make_doc_comment(comment, LINE_LENGTH - INDENT_LENGTH));
-                quote! {
+                Ok(quote! {
                     #comment
                     #default
                     #attributes
                     #key : #typ
-                }
+                })
             })
-            .collect()
+            .collect::<Result<Vec<_>, Error>>()?;
+        if let Some(extra) = self.expand_extra(&schema, !fields.is_empty())? {
+            fields.push(extra);
+        }
+        Ok(fields)
+    }
+
+    /// Collects the properties which are not listed in `properties` into a flattened map when
+    /// `additionalProperties` allows them, so that they survive a round trip through the struct.
+    fn expand_extra(
+        &mut self,
+        schema: &Schema,
+        has_fields: bool,
+    ) -> Result<Option<TokenStream>, Error> {
+        let allowed = match schema.additional_properties {
+            Some(ref props) => !is_false(props),
+            None => false,
+        };
+        if !has_fields || !allowed {
+            return Ok(None);
+        }
+        let mut name = String::from("extra");
+        while self.idents.iter().any(|ident| *ident == name) {
+            name.push('_');
+        }
+        let ident = syn::Ident::new(&name, Span::call_site());
+        self.expander.current_field = name;
+        let typ = self.expander.map_value_type(schema)?;
+        let typ = format!("::std::collections::BTreeMap<String, {}>", typ)
+            .parse::<TokenStream>()
+            .unwrap();
+        if self.expander.validate {
+            let validate = self.expander.validate_path();
+            self.validations.push(quote! {
+                #validate::Validate::validate_at(&self.#ident, pointer, errors);
+            });
+        }
+        self.idents.push(ident.clone());
+        Ok(Some(quote! {
+            /// The properties which are not listed in `properties`.
+            #[serde(flatten)]
+            pub #ident : #typ
+        }))
+    }
+}
+
+/// The version of the JSON Schema specification a schema is written against.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Draft {
+    Draft4,
+    Draft6,
+    Draft7,
+    Draft201909,
+    Draft202012,
+}
+
+impl Draft {
+    /// Recognizes the meta-schema URIs used in `$schema`, such as
+    /// `http://json-schema.org/draft-07/schema#`.
+    pub fn from_uri(uri: &str) -> Option<Draft> {
+        let uri = uri.trim_end_matches('#').trim_end_matches('/');
+        let uri = uri
+            .strip_prefix("http://json-schema.org/")
+            .or_else(|| uri.strip_prefix("https://json-schema.org/"))?;
+        match uri {
+            "draft-04/schema" => Some(Draft::Draft4),
+            "draft-06/schema" => Some(Draft::Draft6),
+            "draft-07/schema" => Some(Draft::Draft7),
+            "draft/2019-09/schema" => Some(Draft::Draft201909),
+            "draft/2020-12/schema" => Some(Draft::Draft202012),
+            _ => None,
+        }
+    }
+
+    /// Determines the draft of a root schema from its `$schema` keyword. Schemas which do not
+    /// declare a (known) draft are assumed to be draft 4.
+    pub fn of(schema: &Schema) -> Draft {
+        schema
+            ._schema
+            .as_ref()
+            .and_then(|uri| Draft::from_uri(uri))
+            .unwrap_or(Draft::Draft4)
     }
 }
 
+impl Schema {
+    /// Compiles the schema into a validator for JSON values. `$ref`s may only point into the
+    /// schema itself, [`SchemaStore::validator`] also resolves references into other documents.
+    pub fn validator(&self) -> Result<Validator, SchemaError> {
+        Validator::new(&self.to_value())
+    }
+}
+
+/// The crates whose types strings with a `format` are generated as. Strings of formats whose
+/// crate is not enabled are generated as `String`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Formats {
+    /// `date-time` and `date` as `chrono::DateTime<chrono::FixedOffset>` and `chrono::NaiveDate`.
+    pub chrono: bool,
+    /// `date-time` and `date` as `time::OffsetDateTime` and `time::Date`, unless `chrono` is
+    /// enabled. As they are only (de)serialized as RFC 3339 through a `#[serde(with = "..")]`
+    /// attribute, this only applies to properties.
+    pub time: bool,
+    /// `uuid` as `uuid::Uuid`.
+    pub uuid: bool,
+    /// `uri` as `url::Url`.
+    pub url: bool,
+    /// `ipv4` and `ipv6` as `std::net::Ipv4Addr` and `std::net::Ipv6Addr`.
+    pub ip: bool,
+}
+
+/// The type which numbers are generated as.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum NumberType {
+    #[default]
+    F64,
+    /// `rust_decimal::Decimal`, which requires the `decimal` feature of `schemafy_core`. Properties
+    /// are (de)serialized with `schemafy_core::decimal`, which reads numbers and strings without
+    /// going through `f64` where their digits are available, while other places use the
+    /// implementations of `rust_decimal`, which write strings.
+    Decimal,
+    /// `schemafy_core::number::Number`, which keeps the digits of numbers with the
+    /// `arbitrary_precision` feature of `schemafy_core`.
+    Number,
+}
+
+/// The document (`None` for the root) and the JSON pointer of a schema.
+type Location = (Option<String>, String);
+
 pub struct Expander<'r> {
     root_name: Option<&'r str>,
     schemafy_path: &'r str,
     root: &'r Schema,
+    /// The root as passed to `new`, which is expanded as the copy kept by the store
+    given_root: &'r Schema,
+    store: Option<&'r SchemaStore>,
+    draft: Draft,
     current_type: String,
     current_field: String,
-    types: Vec<(String, TokenStream)>,
+    /// Whether to generate `Validate` implementations which check the constraint keywords
+    validate: bool,
+    /// Whether deserializing the generated types checks the constraint keywords
+    validate_on_deserialize: bool,
+    /// Whether aliases of constrained types become newtypes enforcing the constraints
+    newtypes: bool,
+    formats: Formats,
+    /// Whether integers are generated as the smallest type fitting their bounds instead of `i64`
+    infer_integers: bool,
+    number_type: NumberType,
+    /// The type of numbers with `format: "decimal"`, if it differs from `number_type`
+    decimal_type: Option<NumberType>,
+    /// Whether an `allOf` with a `$ref` flattens the referenced struct into a field
+    inherit: bool,
+    /// The document (if it is not the root) and the JSON pointer of the schema being expanded
+    document: Option<String>,
+    location: Vec<String>,
+    /// The generated types along with the location of the schema each was generated from
+    types: Vec<(String, Location, TokenStream)>,
+    /// The pairs of a type and a type it holds by value, recorded by the first pass of `expand`
+    held: Option<Vec<(String, String)>>,
+    /// The pairs of a type and a type it holds which are boxed to break cycles
+    boxed: BTreeSet<(String, String)>,
 }
 
 struct FieldType {
@@ -302,98 +619,425 @@ impl<'r> Expander<'r> {
         Expander {
             root_name,
             root,
+            given_root: root,
             schemafy_path,
+            store: None,
+            draft: Draft::of(root),
             current_field: "".into(),
             current_type: "".into(),
+            validate: false,
+            validate_on_deserialize: false,
+            newtypes: false,
+            formats: Formats::default(),
+            infer_integers: false,
+            number_type: NumberType::default(),
+            decimal_type: None,
+            inherit: false,
+            document: None,
+            location: Vec::new(),
             types: Vec::new(),
+            held: None,
+            boxed: BTreeSet::new(),
+        }
+    }
+
+    /// Resolves `$ref`s into other documents using `store`, which must have been loaded for the
+    /// root schema. References are resolved within the copy of the root kept by the store, which
+    /// [`expand`](#method.expand) generates the types of when it is passed the root.
+    pub fn with_store(mut self, store: &'r SchemaStore) -> Expander<'r> {
+        self.root = store.root_schema();
+        self.store = Some(store);
+        self
+    }
+
+    /// Generates an implementation of `schemafy_core::validate::Validate` for every type, which
+    /// checks constraints such as `minimum`, `maxLength` and `pattern`.
+    pub fn with_validation(mut self, validate: bool) -> Expander<'r> {
+        self.validate = validate;
+        self
+    }
+
+    /// Makes deserializing the generated types fail when a value violates the constraint
+    /// keywords, which implies [`with_validation`](#method.with_validation). Structs are
+    /// deserialized through a hidden `...Unchecked` struct and then validated, aliases of
+    /// constrained types become newtypes which validate their value. As nested values are
+    /// validated when they are deserialized, the pointers of the errors are relative to them.
+    pub fn with_validation_on_deserialize(mut self, validate: bool) -> Expander<'r> {
+        self.validate |= validate;
+        self.validate_on_deserialize = validate;
+        self
+    }
+
+    /// Generates newtypes instead of aliases for definitions with constraint keywords, such as
+    /// `{"type": "integer", "minimum": 1}`, which implies
+    /// [`with_validation`](#method.with_validation). The value of a newtype can only be created
+    /// through its checked `new` constructor, `TryFrom` or deserialization.
+    pub fn with_newtypes(mut self, newtypes: bool) -> Expander<'r> {
+        self.validate |= newtypes;
+        self.newtypes = newtypes;
+        self
+    }
+
+    /// Generates strings with a `format` as the types of the enabled crates of `formats`. The
+    /// crates are referred to through their re-exports in `schemafy_core` which must enable the
+    /// features of the same name.
+    pub fn with_formats(mut self, formats: Formats) -> Expander<'r> {
+        self.formats = formats;
+        self
+    }
+
+    /// Generates integers as the smallest of `u8`..`u64` and `i8`..`i64` which holds the values
+    /// allowed by `minimum`, `maximum`, `exclusiveMinimum` and `exclusiveMaximum`, or as the type
+    /// named by a `format` such as `int32` or `uint64`, instead of as `i64`. Bounds beyond the
+    /// range of `i64` give `u64`, `u128` or `i128`.
+    pub fn with_integer_inference(mut self, infer_integers: bool) -> Expander<'r> {
+        self.infer_integers = infer_integers;
+        self
+    }
+
+    /// Generates every number as `number_type` instead of as `f64`.
+    pub fn with_number_type(mut self, number_type: NumberType) -> Expander<'r> {
+        self.number_type = number_type;
+        self
+    }
+
+    /// Generates the numbers with `format: "decimal"` as `decimal_type`, which allows choosing
+    /// the type of single properties.
+    pub fn with_decimal_type(mut self, decimal_type: NumberType) -> Expander<'r> {
+        self.decimal_type = Some(decimal_type);
+        self
+    }
+
+    /// Generates a struct whose `allOf` refers to another struct with a `#[serde(flatten)] base`
+    /// field of the referenced type and its own fields, dereferencing to the base, instead of a
+    /// copy of the fields of both. Only the first `$ref` of an `allOf` becomes the base, any
+    /// other is copied as before, as is a base whose properties the struct redefines.
+    pub fn with_inheritance(mut self, inherit: bool) -> Expander<'r> {
+        self.inherit = inherit;
+        self
+    }
+
+    /// Returns the draft used to interpret the schema.
+    pub fn draft(&self) -> Draft {
+        self.draft
+    }
+
+    /// Returns the location of the schema being expanded.
+    fn location(&self) -> Location {
+        (self.document.clone(), pointer::join(&self.location))
+    }
+
+    /// Returns an error located at the schema being expanded.
+    fn error(&self, reason: impl Into<String>) -> Error {
+        let (document, pointer) = self.location();
+        Error::new(document, pointer, reason.into())
+    }
+
+    /// Records a type generated from the schema being expanded.
+    fn push_type(&mut self, name: String, tokens: TokenStream) {
+        let location = self.location();
+        self.types.push((name, location, tokens));
+    }
+
+    /// Runs `f` with the location moved to the subschema at `tokens` (relative to the current
+    /// location).
+    fn at<T>(
+        &mut self,
+        tokens: &[impl AsRef<str>],
+        f: impl FnOnce(&mut Self) -> Result<T, Error>,
+    ) -> Result<T, Error> {
+        let len = self.location.len();
+        self.location
+            .extend(tokens.iter().map(|token| token.as_ref().to_string()));
+        let result = f(self);
+        self.location.truncate(len);
+        result
+    }
+
+    /// Returns the only value `schema` accepts, if it is restricted to a single value by `enum`
+    /// or (since draft 6) `const`.
+    fn constant<'s>(&self, schema: &'s Schema) -> Option<&'s Value> {
+        match schema.enum_.as_ref().map(|e| &e[..]) {
+            Some([value]) => Some(value),
+            _ if self.draft >= Draft::Draft6 => schema.const_.as_ref(),
+            _ => None,
         }
     }
 
-    fn type_ref(&self, s: &str) -> String {
-        let s = if s == "#" {
-            self.root_name.expect("No root name specified for schema")
+    /// Returns the document a reference points into along with the fragment within it.
+    fn document<'s>(&self, s: &'s str) -> Result<(&'r Schema, &'s str), Error> {
+        let (uri, fragment) = store::split_ref(s);
+        if uri.is_empty() {
+            return Ok((self.root, fragment));
+        }
+        let store = self
+            .store
+            .ok_or_else(|| self.error(format!("No schema store to resolve `{}`", s)))?;
+        let uri = store::resolve_uri(store.root(), uri);
+        let (document, pointer) = store.locate(&uri);
+        let document = if document == store.root() {
+            self.root
         } else {
-            s.split('/').last().expect("Component")
+            store
+                .document(document)
+                .ok_or_else(|| self.error(format!("Expected document: `{}`", uri)))?
         };
-        let s = &s.to_pascal_case();
-        let s = replace_invalid_identifier_chars(&s);
-        replace_numeric_start(&s)
+        // Schemas identified by an `id` are located within their document
+        match pointer::resolve(document, pointer) {
+            Some(Cow::Borrowed(document)) => Ok((document, fragment)),
+            _ => Err(self.error(format!("Expected a schema at `{}`", uri))),
+        }
     }
 
-    fn schema(&self, schema: &'r Schema) -> Cow<'r, Schema> {
-        let schema = match schema.ref_ {
-            Some(ref ref_) => self.schema_ref(ref_),
-            None => schema,
+    fn type_ref(&self, s: &str) -> Result<String, Error> {
+        let (document, fragment) = self.document(s)?;
+        let s = if fragment.is_empty() && std::ptr::eq(document, self.root) {
+            self.root_name.ok_or_else(|| {
+                self.error(format!("No root name specified for schema to resolve `{}`", s))
+            })?
+        } else if fragment.is_empty() {
+            // A whole document is named after its title or, failing that, its file
+            match document.title {
+                Some(ref title) => title,
+                None => {
+                    let uri = store::split_ref(s).0;
+                    Path::new(uri).file_stem().and_then(|s| s.to_str()).unwrap_or(uri)
+                }
+            }
+        } else if let Some(anchor) = anchor(fragment) {
+            let (tokens, _) = self
+                .find_anchor(document, anchor)
+                .ok_or_else(|| self.error(format!("Expected anchor: `{}`", s)))?;
+            return Ok(type_name(tokens.last().unwrap()));
+        } else {
+            // Report dangling references here rather than as an unknown type in the output
+            self.schema_ref(s)?;
+            return Ok(type_name(&pointer::name(fragment)));
         };
-        match schema.all_of {
-            Some(ref all_of) if !all_of.is_empty() => {
+        Ok(type_name(s))
+    }
+
+    fn schema(&self, schema: &'r Schema) -> Result<Cow<'r, Schema>, Error> {
+        let schema = match schema._ref {
+            Some(ref ref_) => self.schema_ref(ref_)?,
+            None => Cow::Borrowed(schema),
+        };
+        match schema {
+            Cow::Borrowed(schema) => self.resolve_all_of(schema),
+            Cow::Owned(schema) => {
+                let expander: &Expander<'_> = self;
+                Ok(Cow::Owned(expander.resolve_all_of(&schema)?.into_owned()))
+            }
+        }
+    }
+
+    /// Returns the type of the base struct of `schema` along with the merged parts of its `allOf`
+    /// besides the base, see [`with_inheritance`](#method.with_inheritance).
+    fn inherited_base(&self, schema: &Schema) -> Result<Option<(String, Schema)>, Error> {
+        let all_of = match schema.all_of {
+            Some(ref all_of) if self.inherit && schema._ref.is_none() => all_of,
+            _ => return Ok(None),
+        };
+        let (index, ref_) = match all_of
+            .iter()
+            .enumerate()
+            .find_map(|(index, part)| part._ref.as_ref().map(|ref_| (index, ref_)))
+        {
+            Some(base) => base,
+            None => return Ok(None),
+        };
+        let expander: &Expander<'_> = self;
+        let base = expander.schema(&all_of[index])?;
+        if base.properties.is_empty() || !base.pattern_properties.is_empty() {
+            return Ok(None);
+        }
+        let own = Schema {
+            all_of: Some(
                 all_of
                     .iter()
-                    .skip(1)
-                    .fold(self.schema(&all_of[0]).clone(), |mut result, def| {
-                        merge_all_of(result.to_mut(), &self.schema(def));
-                        result
-                    })
+                    .enumerate()
+                    .filter(|&(i, _)| i != index)
+                    .map(|(_, part)| part.clone())
+                    .collect(),
+            ),
+            ..schema.clone()
+        };
+        let own = expander.schema(&own)?.into_owned();
+        if own.properties.keys().any(|key| base.properties.contains_key(key)) {
+            return Ok(None);
+        }
+        Ok(Some((self.type_ref(ref_)?, own)))
+    }
+
+    /// Merges the schemas of an `allOf` along with the keywords beside it into a single schema,
+    /// whose `allOf` only holds the parts which could not be merged.
+    fn resolve_all_of(&self, schema: &'r Schema) -> Result<Cow<'r, Schema>, Error> {
+        match schema.all_of {
+            Some(ref all_of) if !all_of.is_empty() => {
+                let mut result = Schema {
+                    all_of: None,
+                    ..schema.clone()
+                };
+                for (i, part) in all_of.iter().enumerate() {
+                    let part = self.schema(part)?;
+                    all_of::merge(&mut result, &part).map_err(|err| {
+                        self.error(format!("`allOf/{}` contradicts the other schemas: {}", i, err))
+                    })?;
+                }
+                Ok(Cow::Owned(result))
             }
-            _ => Cow::Borrowed(schema),
+            _ => Ok(Cow::Borrowed(schema)),
         }
     }
 
-    fn schema_ref(&self, s: &str) -> &'r Schema {
-        s.split('/').fold(self.root, |schema, comp| {
-            if comp == "#" {
-                self.root
-            } else if comp == "definitions" {
-                schema
+    fn schema_ref(&self, s: &str) -> Result<Cow<'r, Schema>, Error> {
+        let (document, fragment) = self.document(s)?;
+        if let Some(anchor) = anchor(fragment) {
+            return self
+                .find_anchor(document, anchor)
+                .map(|(_, schema)| Cow::Borrowed(schema))
+                .ok_or_else(|| self.error(format!("Expected anchor: `{}`", s)));
+        }
+        pointer::resolve(document, fragment)
+            .or_else(|| self.resolve_value(s).map(Cow::Owned))
+            .ok_or_else(|| self.error(format!("Expected a schema at `{}`", s)))
+    }
+
+    /// Resolves `s` within the JSON of its document, which also contains the values of unknown
+    /// keywords such as `{ "integer": { "type": "integer" } }`.
+    fn resolve_value(&self, s: &str) -> Option<Schema> {
+        let store = self.store?;
+        let (uri, fragment) = store::split_ref(s);
+        let uri = store::resolve_uri(store.root(), uri);
+        let (document, pointer) = store.locate(&uri);
+        let tokens = [pointer::tokens(pointer)?, pointer::tokens(fragment)?].concat();
+        pointer::resolve_value(store.value(document)?, &tokens)
+    }
+
+    /// Searches the definitions of `schema` for the one declaring `$anchor: anchor`, returning
+    /// the reference tokens of its pointer within `schema` along with it.
+    fn find_anchor(&self, schema: &'r Schema, anchor: &str) -> Option<(Vec<String>, &'r Schema)> {
+        let definitions = schema.definitions.iter().map(|def| ("definitions", def));
+        let defs = schema._defs.iter().map(|def| ("$defs", def));
+        definitions.chain(defs).find_map(|(keyword, (name, def))| {
+            let tokens = vec![keyword.to_string(), name.clone()];
+            // Before `$anchor`, `id: "#foo"` served the same purpose
+            let id = def._id.as_ref().or(def.id.as_ref());
+            let id_anchor = id.map(|id| store::split_ref(id).1);
+            if def._anchor.as_deref() == Some(anchor) || id_anchor == Some(anchor) {
+                Some((tokens, def))
             } else {
-                schema
-                    .definitions
-                    .get(comp)
-                    .unwrap_or_else(|| panic!("Expected definition: `{}` {}", s, comp))
+                self.find_anchor(def, anchor)
+                    .map(|(nested, def)| ([tokens, nested].concat(), def))
             }
         })
     }
 
-    fn expand_type(&mut self, type_name: &str, required: bool, typ: &Schema) -> FieldType {
-        let mut result = self.expand_type_(typ);
-        if type_name.to_pascal_case() == result.typ.to_pascal_case() {
-            result.typ = format!("Box<{}>", result.typ)
+    /// Returns the location of the schema `s` refers to, which is the same whichever document
+    /// refers to it.
+    fn location_of(&self, s: &str) -> Result<Location, Error> {
+        let (uri, fragment) = store::split_ref(s);
+        let (document, mut tokens) = match self.store {
+            Some(store) if !uri.is_empty() => {
+                let uri = store::resolve_uri(store.root(), uri);
+                let (document, pointer) = store.locate(&uri);
+                (
+                    Some(document.to_string()).filter(|document| document != store.root()),
+                    pointer::tokens(pointer).unwrap_or_default(),
+                )
+            }
+            _ => (None, Vec::new()),
+        };
+        match anchor(fragment) {
+            Some(anchor) => {
+                let (nested, _) = self
+                    .find_anchor(self.document(s)?.0, anchor)
+                    .ok_or_else(|| self.error(format!("Expected anchor: `{}`", s)))?;
+                tokens.extend(nested);
+            }
+            None => tokens.extend(
+                pointer::tokens(fragment)
+                    .ok_or_else(|| self.error(format!("Expected a JSON pointer: `{}`", s)))?,
+            ),
         }
+        Ok((document, pointer::join(&tokens)))
+    }
+
+    fn expand_type(
+        &mut self,
+        type_name: &str,
+        required: bool,
+        typ: &Schema,
+    ) -> Result<FieldType, Error> {
+        let mut result = self.expand_type_(typ)?;
+        result.typ = self.indirect(type_name, result.typ);
         if !required && !result.default {
             result.typ = format!("Option<{}>", result.typ)
         }
-        result
+        Ok(result)
     }
 
-    fn expand_type_(&mut self, typ: &Schema) -> FieldType {
-        if let Some(ref ref_) = typ.ref_ {
-            self.type_ref(ref_).into()
-        } else if typ.any_of.as_ref().map_or(false, |a| a.len() == 2) {
-            let any_of = typ.any_of.as_ref().unwrap();
-            let simple = self.schema(&any_of[0]);
-            let array = self.schema(&any_of[1]);
-            if !array.type_.is_empty() {
-                if let SimpleTypes::Array = array.type_[0] {
-                    if simple == self.schema(&array.items[0]) {
-                        return FieldType {
-                            typ: format!("Vec<{}>", self.expand_type_(&any_of[0]).typ),
-                            attributes: vec![format!(
-                                r#"with="{}one_or_many""#,
-                                self.schemafy_path
-                            )],
-                            default: true,
-                        };
-                    }
+    fn expand_type_(&mut self, typ: &Schema) -> Result<FieldType, Error> {
+        Ok(if let Some(ref ref_) = typ._ref {
+            self.type_ref(ref_)?.into()
+        } else if let Some(ref_) = annotated_ref(typ) {
+            self.type_ref(ref_)?.into()
+        } else if typ.all_of.is_some() {
+            let merged = {
+                let expander: &Expander<'_> = self;
+                expander.schema(typ)?.into_owned()
+            };
+            // The parts which could not be merged, such as a second `pattern`, are only checked
+            // by the validation and do not change the type
+            self.expand_type_(&Schema {
+                all_of: None,
+                ..merged
+            })?
+        } else if let Some(field_type) = self.expand_one_or_many(typ)? {
+            field_type
+        } else if let Some((keyword, alternatives)) = union_alternatives(typ) {
+            let name = format!(
+                "{}{}",
+                self.current_type.to_pascal_case(),
+                self.current_field.to_pascal_case()
+            );
+            let (alternatives, nullable) = split_null_alternative(alternatives);
+            let typ = if let [(index, alternative)] = alternatives[..] {
+                self.at(&[keyword, &index.to_string()], |expander| {
+                    expander.expand_type_(alternative)
+                })?
+                .typ
+            } else {
+                let tokens = self.at(&[keyword], |expander| {
+                    expander.expand_union(&name, &alternatives)
+                })?;
+                self.push_type(name.clone(), tokens);
+                name
+            };
+            if nullable {
+                FieldType {
+                    typ: format!("Option<{}>", typ),
+                    attributes: vec![],
+                    default: true,
                 }
+            } else {
+                typ.into()
+            }
+        } else if let (true, Some(value)) = (typ.type_.is_empty(), self.constant(typ)) {
+            match value {
+                Value::Bool(_) => "bool".into(),
+                Value::Number(n) if n.is_f64() => "f64".into(),
+                Value::Number(_) => "i64".into(),
+                Value::String(_) => "String".into(),
+                _ => "serde_json::Value".into(),
             }
-            return "serde_json::Value".into();
         } else if typ.type_.len() == 2 {
             if typ.type_[0] == SimpleTypes::Null || typ.type_[1] == SimpleTypes::Null {
                 let mut ty = typ.clone();
                 ty.type_.retain(|x| *x != SimpleTypes::Null);
 
                 FieldType {
-                    typ: format!("Option<{}>", self.expand_type_(&ty).typ),
+                    typ: format!("Option<{}>", self.expand_type_(&ty)?.typ),
                     attributes: vec![],
                     default: true,
                 }
@@ -405,103 +1049,1013 @@ impl<'r> Expander<'r> {
                 SimpleTypes::String => {
                     if typ.enum_.as_ref().map_or(false, |e| e.is_empty()) {
                         "serde_json::Value".into()
+                    } else if let Some(format_type) = self.format_type(typ) {
+                        format_type.into()
                     } else {
                         "String".into()
                     }
                 }
-                SimpleTypes::Integer => "i64".into(),
+                SimpleTypes::Integer => self.integer_type(typ).into(),
                 SimpleTypes::Boolean => "bool".into(),
-                SimpleTypes::Number => "f64".into(),
+                SimpleTypes::Number => self.number_type(typ).into(),
                 // Handle objects defined inline
                 SimpleTypes::Object
-                    if !typ.properties.is_empty()
-                        || typ.additional_properties == Some(Value::Bool(false)) =>
+                    if !typ.properties.is_empty() || is_closed(typ) =>
                 {
                     let name = format!(
                         "{}{}",
                         self.current_type.to_pascal_case(),
                         self.current_field.to_pascal_case()
                     );
-                    let tokens = self.expand_schema(&name, typ);
-                    self.types.push((name.clone(), tokens));
+                    let tokens = self.expand_schema(&name, typ)?;
+                    self.push_type(name.clone(), tokens);
                     name.into()
                 }
                 SimpleTypes::Object => {
-                    let prop = match typ.additional_properties {
-                        Some(ref props) if props.is_object() => {
-                            let prop = serde_json::from_value(props.clone()).unwrap();
-                            self.expand_type_(&prop).typ
-                        }
-                        _ => "serde_json::Value".into(),
-                    };
-                    let result = format!("::std::collections::BTreeMap<String, {}>", prop);
+                    let key = self.map_key_type(typ)?;
+                    let prop = self.map_value_type(typ)?;
+                    let result = format!("::std::collections::BTreeMap<{}, {}>", key, prop);
                     FieldType {
                         typ: result,
                         attributes: Vec::new(),
                         default: typ.default == Some(Value::Object(Default::default())),
                     }
                 }
+                // Arrays whose items are given by position have items of differing types
+                SimpleTypes::Array if typ.positional_items().is_some() => {
+                    match self.tuple_type(typ)? {
+                        Some(tuple) => tuple.into(),
+                        None => "Vec<serde_json::Value>".into(),
+                    }
+                }
                 SimpleTypes::Array => {
-                    let item_type = typ.items.get(0).map_or("serde_json::Value".into(), |item| {
-                        self.current_type = format!("{}Item", self.current_type);
-                        self.expand_type_(item).typ
-                    });
-                    format!("Vec<{}>", item_type).into()
+                    let item_type = match typ.items_schema() {
+                        Some(item) => self.at(&["items"], |expander| {
+                            expander.current_type = format!("{}Item", expander.current_type);
+                            Ok(expander.expand_type_(item)?.typ)
+                        })?,
+                        None => "serde_json::Value".into(),
+                    };
+                    match fixed_length(typ) {
+                        Some(len) => format!("[{}; {}]", item_type, len).into(),
+                        None => format!("Vec<{}>", item_type).into(),
+                    }
                 }
                 _ => "serde_json::Value".into(),
             }
         } else {
             "serde_json::Value".into()
+        })
+    }
+
+    /// Returns the type of the values of a map. It is only more specific than `serde_json::Value`
+    /// if `patternProperties` and `additionalProperties` (or `unevaluatedProperties`) describe the
+    /// values of every property, values of differing types being combined in an untagged union.
+    fn map_value_type(&mut self, typ: &Schema) -> Result<String, Error> {
+        let mut alternatives = typ
+            .pattern_properties
+            .iter()
+            .map(|(pattern, schema)| (vec!["patternProperties", &pattern[..]], schema.clone()))
+            .collect::<Vec<_>>();
+        let any_name = typ
+            .pattern_properties
+            .keys()
+            .any(|pattern| matches_every_name(pattern));
+        match typ.additional_properties.as_deref() {
+            Some(props) if is_false(props) => (),
+            Some(props) if *props != Schema::default() => {
+                alternatives.push((vec!["additionalProperties"], props.clone()))
+            }
+            None => match typ.unevaluated_properties {
+                Some(ref prop) if is_false(prop) => (),
+                Some(ref prop) => {
+                    alternatives.push((vec!["unevaluatedProperties"], (**prop).clone()))
+                }
+                None if any_name => (),
+                None => return Ok("serde_json::Value".into()),
+            },
+            _ if any_name => (),
+            _ => return Ok("serde_json::Value".into()),
+        }
+
+        let current_field = self.current_field.clone();
+        let mut types = Vec::<(String, usize)>::new();
+        for (i, (location, schema)) in alternatives.iter().enumerate() {
+            if alternatives.len() > 1 {
+                self.current_field = format!("{}Value{}", current_field, i);
+            }
+            let typ = self.at(location, |expander| expander.expand_type_(schema));
+            self.current_field.clone_from(&current_field);
+            let typ = typ?.typ;
+            if !types.iter().any(|(t, _)| *t == typ) {
+                types.push((typ, i));
+            }
         }
+        Ok(match &types[..] {
+            [] => "serde_json::Value".into(),
+            [(typ, _)] => typ.clone(),
+            _ => {
+                let name = format!(
+                    "{}{}Value",
+                    self.current_type.to_pascal_case(),
+                    current_field.to_pascal_case()
+                );
+                let alternatives = types
+                    .iter()
+                    .map(|&(_, i)| (i, &alternatives[i].1))
+                    .collect::<Vec<_>>();
+                let tokens = self.expand_union(&name, &alternatives)?;
+                self.push_type(name.clone(), tokens);
+                name
+            }
+        })
     }
 
-    fn expand_definitions(&mut self, schema: &Schema) {
-        for (name, def) in &schema.definitions {
-            let type_decl = self.expand_schema(name, def);
-            let definition_tokens = match def.description {
-                Some(ref comment) => {
-                    let t = make_doc_comment(comment, LINE_LENGTH);
-                    quote! {
-                        #t
-                        #type_decl
+    /// Returns the type of the keys of a map, an enum if `propertyNames` lists the names.
+    fn map_key_type(&mut self, typ: &Schema) -> Result<String, Error> {
+        let names = match typ.property_names {
+            Some(ref names) => names,
+            None => return Ok("String".into()),
+        };
+        let resolved = match names._ref {
+            Some(ref ref_) => self.schema_ref(ref_)?,
+            None => Cow::Borrowed(&**names),
+        };
+        let is_enum = match resolved.enum_ {
+            Some(ref values) => !values.is_empty() && values.iter().all(Value::is_string),
+            None => false,
+        };
+        if !is_enum {
+            return Ok("String".into());
+        }
+        if let Some(ref ref_) = names._ref {
+            return self.type_ref(ref_);
+        }
+        let name = format!(
+            "{}{}Key",
+            self.current_type.to_pascal_case(),
+            self.current_field.to_pascal_case()
+        );
+        let tokens = self.at(&["propertyNames"], |expander| {
+            expander.expand_schema(&name, names)
+        })?;
+        self.push_type(name.clone(), tokens);
+        Ok(name)
+    }
+
+    /// Returns the type of an integer schema, see
+    /// [`with_integer_inference`](#method.with_integer_inference).
+    fn integer_type(&self, typ: &Schema) -> &'static str {
+        if !self.infer_integers {
+            return "i64";
+        }
+        if let Some(integer) = typ.format.as_deref().and_then(integer_format) {
+            return integer;
+        }
+        let mut lower = typ.minimum.map(f64::ceil);
+        let mut upper = typ.maximum.map(f64::floor);
+        match typ.exclusive_minimum {
+            Some(ExclusiveLimit::Boolean(true)) => lower = typ.minimum.map(|min| min.floor() + 1.0),
+            Some(ExclusiveLimit::Number(min)) => {
+                lower = Some(lower.map_or(min.floor() + 1.0, |lower| lower.max(min.floor() + 1.0)))
+            }
+            _ => (),
+        }
+        match typ.exclusive_maximum {
+            Some(ExclusiveLimit::Boolean(true)) => upper = typ.maximum.map(|max| max.ceil() - 1.0),
+            Some(ExclusiveLimit::Number(max)) => {
+                upper = Some(upper.map_or(max.ceil() - 1.0, |upper| upper.min(max.ceil() - 1.0)))
+            }
+            _ => (),
+        }
+        fitting_integer(lower, upper)
+    }
+
+    /// Returns which type a number schema is generated as.
+    fn number_kind(&self, typ: &Schema) -> NumberType {
+        match typ.format.as_deref() {
+            Some("decimal") => self.decimal_type.unwrap_or(self.number_type),
+            _ => self.number_type,
+        }
+    }
+
+    /// Returns the type of a number schema.
+    fn number_type(&self, typ: &Schema) -> String {
+        match self.number_kind(typ) {
+            NumberType::F64 => "f64".into(),
+            NumberType::Decimal => format!("{}rust_decimal::Decimal", self.schemafy_path),
+            NumberType::Number => format!("{}number::Number", self.schemafy_path),
+        }
+    }
+
+    /// Returns the type of the enabled crate for the `format` of a string schema.
+    fn format_type(&self, typ: &Schema) -> Option<String> {
+        let path = self.schemafy_path;
+        Some(match typ.format.as_deref()? {
+            "date-time" if self.formats.chrono => format!(
+                "{}chrono::DateTime<{}chrono::FixedOffset>",
+                path, path
+            ),
+            "date" if self.formats.chrono => format!("{}chrono::NaiveDate", path),
+            "uuid" if self.formats.uuid => format!("{}uuid::Uuid", path),
+            "uri" if self.formats.url => format!("{}url::Url", path),
+            "ipv4" if self.formats.ip => "::std::net::Ipv4Addr".into(),
+            "ipv6" if self.formats.ip => "::std::net::Ipv6Addr".into(),
+            _ => return None,
+        })
+    }
+
+    /// Returns the `time` type of a property with a `date-time` or `date` format, which is
+    /// (de)serialized with `schemafy_core::rfc3339`.
+    fn time_field_type(&self, typ: &Schema, required: bool) -> Option<FieldType> {
+        if !self.formats.time || self.formats.chrono || typ.type_ != [SimpleTypes::String] {
+            return None;
+        }
+        let typ = match typ.format.as_deref()? {
+            "date-time" => format!("{}time::OffsetDateTime", self.schemafy_path),
+            "date" => format!("{}time::Date", self.schemafy_path),
+            _ => return None,
+        };
+        let attributes = vec![format!(r#"with = "{}rfc3339""#, self.schemafy_path)];
+        Some(if required {
+            FieldType {
+                typ,
+                attributes,
+                default: false,
+            }
+        } else {
+            // Fields deserialized `with` a module are only optional if they have a default
+            FieldType {
+                typ: format!("Option<{}>", typ),
+                attributes,
+                default: true,
+            }
+        })
+    }
+
+    /// Returns the `rust_decimal::Decimal` type of a number property, which is (de)serialized with
+    /// `schemafy_core::decimal` so that its digits do not go through `f64`.
+    fn decimal_field_type(&self, typ: &Schema, required: bool) -> Option<FieldType> {
+        if typ.type_ != [SimpleTypes::Number] || self.number_kind(typ) != NumberType::Decimal {
+            return None;
+        }
+        let typ = self.number_type(typ);
+        let attributes = vec![format!(r#"with = "{}decimal""#, self.schemafy_path)];
+        Some(if required {
+            FieldType {
+                typ,
+                attributes,
+                default: false,
+            }
+        } else {
+            FieldType {
+                typ: format!("Option<{}>", typ),
+                attributes,
+                default: true,
+            }
+        })
+    }
+
+    /// Returns a tuple of the items of an array whose items are given by position (`prefixItems`
+    /// or an array of `items`), if every position must be present and no other items are
+    /// allowed.
+    fn tuple_type(&mut self, typ: &Schema) -> Result<Option<String>, Error> {
+        let (keyword, closed) = match typ.prefix_items {
+            Some(_) => ("prefixItems", matches!(typ.items_schema(), Some(s) if is_false(s))),
+            None => (
+                "items",
+                matches!(typ.additional_items.as_deref(), Some(s) if is_false(s)),
+            ),
+        };
+        let positions = typ.positional_items().unwrap_or_default();
+        let len = positions.len() as u64;
+        let min_items = typ.min_items.and_then(|n| u64::try_from(n).ok()).unwrap_or(0);
+        let max_items = typ.max_items.and_then(|max| u64::try_from(max).ok());
+        let closed = closed || matches!(max_items, Some(max) if max <= len);
+        // serde (de)serializes tuples of up to 16 items
+        if !closed || min_items < len || len == 0 || len > 16 {
+            return Ok(None);
+        }
+        let current_type = self.current_type.clone();
+        let items = positions
+            .iter()
+            .enumerate()
+            .map(|(i, item)| {
+                self.at(&[keyword, &i.to_string()], |expander| {
+                    expander.current_type = format!("{}Item{}", current_type, i);
+                    Ok(expander.expand_type_(item)?.typ)
+                })
+            })
+            .collect::<Result<Vec<_>, Error>>();
+        self.current_type = current_type;
+        Ok(Some(format!("({},)", items?.join(", "))))
+    }
+
+    /// Recognizes `anyOf: [T, { type: array, items: T }]` which is deserialized as a `Vec<T>`
+    /// accepting either a single value or an array of values.
+    fn expand_one_or_many(&mut self, typ: &Schema) -> Result<Option<FieldType>, Error> {
+        let any_of = match typ.any_of {
+            Some(ref any_of) if any_of.len() == 2 => any_of,
+            _ => return Ok(None),
+        };
+        let simple = self.schema(&any_of[0])?;
+        let array = self.schema(&any_of[1])?;
+        let is_one_or_many = array.type_.first() == Some(&SimpleTypes::Array)
+            && match array.items_schema() {
+                Some(item) => self.schema(item)? == simple,
+                None => false,
+            };
+        if is_one_or_many {
+            let typ = self.at(&["anyOf", "0"], |expander| expander.expand_type_(&any_of[0]))?;
+            Ok(Some(FieldType {
+                typ: format!("Vec<{}>", typ.typ),
+                attributes: vec![format!(r#"with="{}one_or_many""#, self.schemafy_path)],
+                default: true,
+            }))
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn variant_name(&self, index: usize, schema: &Schema) -> Result<String, Error> {
+        let name = if let Some(ref ref_) = schema._ref {
+            return self.type_ref(ref_);
+        } else if let Some(ref title) = schema.title {
+            title.to_pascal_case()
+        } else if let Some(Value::String(value)) = self.constant(schema) {
+            value.to_pascal_case()
+        } else if schema.type_.len() == 1 {
+            match schema.type_[0] {
+                SimpleTypes::Array => "Array",
+                SimpleTypes::Boolean => "Boolean",
+                SimpleTypes::Integer => "Integer",
+                SimpleTypes::Null => "Null",
+                SimpleTypes::Number => "Number",
+                SimpleTypes::Object => "Object",
+                SimpleTypes::String => "String",
+            }
+            .to_string()
+        } else {
+            String::new()
+        };
+        Ok(if name.is_empty() {
+            format!("Variant{}", index)
+        } else {
+            replace_numeric_start(&replace_invalid_identifier_chars(&name))
+        })
+    }
+
+    /// Boxes the types which `typ` holds by value if they would otherwise make the type
+    /// `container` hold itself.
+    ///
+    /// During the first pass of `expand` nothing is boxed yet, the references are only recorded.
+    fn indirect(&mut self, container: &str, typ: String) -> String {
+        let container = type_name(container);
+        let held = cycles::held_types(&typ);
+        if let Some(ref mut edges) = self.held {
+            edges.extend(
+                held.into_iter()
+                    .map(|range| (container.clone(), typ[range].to_string())),
+            );
+            return typ;
+        }
+        let mut result = typ.clone();
+        for range in held.into_iter().rev() {
+            let edge = (container.clone(), typ[range.clone()].to_string());
+            if self.boxed.contains(&edge) {
+                result.replace_range(range, &format!("Box<{}>", edge.1));
+            }
+        }
+        result
+    }
+
+    /// Generates an untagged enum with one variant per alternative of a `oneOf` or `anyOf`.
+    ///
+    /// Each alternative is paired with its index in the `oneOf` or `anyOf`.
+    fn expand_union(
+        &mut self,
+        name: &str,
+        alternatives: &[(usize, &Schema)],
+    ) -> Result<TokenStream, Error> {
+        if let Some((tag, values)) = self.discriminator(alternatives)? {
+            return self.expand_tagged_union(name, &tag, alternatives, &values);
+        }
+        let enclosing_type = self.current_type.clone();
+        let enclosing_field = self.current_field.clone();
+        let validate = self.validate_path();
+        let mut validations = Vec::new();
+        let mut variant_names = Vec::<String>::new();
+        let variants = alternatives
+            .iter()
+            .map(|&(index, alternative)| {
+                self.at(&[index.to_string()], |expander| {
+                    let mut variant_name = expander.variant_name(index, alternative)?;
+                    if variant_names.contains(&variant_name) {
+                        variant_name = format!("{}{}", variant_name, index);
+                    }
+                    variant_names.push(variant_name.clone());
+
+                    expander.current_type = name.to_string();
+                    expander.current_field.clone_from(&variant_name);
+                    let typ = expander.expand_type_(alternative)?.typ;
+                    let typ = expander.indirect(name, typ).parse::<TokenStream>().unwrap();
+                    let variant_name = str_to_ident(&variant_name);
+                    if expander.validate {
+                        let check = expander.check_constraints(alternative, quote!(value))?;
+                        validations.push(quote! {
+                            #variant_name(value) => {
+                                #check
+                                #validate::Validate::validate_at(value, pointer, errors);
+                            }
+                        });
+                    }
+                    let comment = alternative
+                        .description
+                        .as_ref()
+                        .map(|comment| make_doc_comment(comment, LINE_LENGTH - INDENT_LENGTH));
+                    Ok(quote! {
+                        #comment
+                        #variant_name(#typ)
+                    })
+                })
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+        self.current_type = enclosing_type;
+        self.current_field = enclosing_field;
+
+        let name = str_to_ident(name);
+        let validation = self.impl_validate(
+            &name,
+            quote! {
+                match self {
+                    #(#name::#validations)*
+                }
+            },
+        );
+        Ok(quote! {
+            #[derive(Clone, PartialEq, Debug, Deserialize, Serialize)]
+            #[serde(untagged)]
+            pub enum #name {
+                #(#variants),*
+            }
+            #validation
+        })
+    }
+
+    /// Finds a required property which has a distinct constant value in every alternative.
+    fn discriminator(
+        &self,
+        alternatives: &[(usize, &Schema)],
+    ) -> Result<Option<(String, Vec<String>)>, Error> {
+        let alternatives = alternatives
+            .iter()
+            .map(|(_, alternative)| self.schema(alternative))
+            .collect::<Result<Vec<_>, Error>>()?;
+        let first = match alternatives.first() {
+            Some(first) => first,
+            None => return Ok(None),
+        };
+        Ok(first.properties.keys().find_map(|tag| {
+            let values = alternatives
+                .iter()
+                .map(|alternative| {
+                    let required = alternative.required.as_ref()?.contains(tag);
+                    // Properties which fail to resolve are reported once their field is expanded
+                    let property = self.schema(alternative.properties.get(tag)?).ok()?;
+                    match self.constant(&property) {
+                        Some(Value::String(value)) if required => Some(value.clone()),
+                        _ => None,
                     }
+                })
+                .collect::<Option<Vec<_>>>()?;
+            let distinct = values
+                .iter()
+                .enumerate()
+                .all(|(i, value)| !values[..i].contains(value));
+            if distinct {
+                Some((tag.clone(), values))
+            } else {
+                None
+            }
+        }))
+    }
+
+    /// Generates an internally tagged enum where each variant holds the fields of its alternative
+    /// minus the `tag` property.
+    fn expand_tagged_union(
+        &mut self,
+        name: &str,
+        tag: &str,
+        alternatives: &[(usize, &Schema)],
+        values: &[String],
+    ) -> Result<TokenStream, Error> {
+        let enclosing_type = self.current_type.clone();
+        let enclosing_field = self.current_field.clone();
+        let validate = self.validate_path();
+        let mut validations = Vec::new();
+        let variants = alternatives
+            .iter()
+            .zip(values)
+            .map(|(&(index, alternative), value)| {
+                self.at(&[index.to_string()], |expander| {
+                    let mut schema = expander.schema(alternative)?.into_owned();
+                    schema.properties.remove(tag);
+                    if let Some(ref mut required) = schema.required {
+                        required.retain(|r| r != tag);
+                    }
+                    schema.definitions.clear();
+                    schema._defs.clear();
+
+                    let variant_name = type_name(value);
+                    let variant = str_to_ident(&variant_name);
+                    let comment = schema
+                        .description
+                        .as_ref()
+                        .map(|comment| make_doc_comment(comment, LINE_LENGTH - INDENT_LENGTH));
+                    Ok(if schema.properties.is_empty() {
+                        validations.push(quote! { #variant => (), });
+                        quote! {
+                            #comment
+                            #[serde(rename = #value)]
+                            #variant
+                        }
+                    } else {
+                        let type_name = format!("{}{}", name, variant_name);
+                        let tokens = expander.expand_schema(&type_name, &schema)?;
+                        expander.push_type(type_name.clone(), tokens);
+                        let typ = expander
+                            .indirect(name, type_name)
+                            .parse::<TokenStream>()
+                            .unwrap();
+                        validations.push(quote! {
+                            #variant(value) => #validate::Validate::validate_at(value, pointer, errors),
+                        });
+                        quote! {
+                            #comment
+                            #[serde(rename = #value)]
+                            #variant(#typ)
+                        }
+                    })
+                })
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+        self.current_type = enclosing_type;
+        self.current_field = enclosing_field;
+
+        let name = str_to_ident(name);
+        let validation = self.impl_validate(
+            &name,
+            quote! {
+                match self {
+                    #(#name::#validations)*
+                }
+            },
+        );
+        Ok(quote! {
+            #[derive(Clone, PartialEq, Debug, Deserialize, Serialize)]
+            #[serde(tag = #tag)]
+            pub enum #name {
+                #(#variants),*
+            }
+            #validation
+        })
+    }
+
+    fn validate_path(&self) -> TokenStream {
+        format!("{}validate", self.schemafy_path)
+            .parse::<TokenStream>()
+            .unwrap()
+    }
+
+    /// Implements `Validate` for the type `name` by running `body`, which may refer to `pointer`
+    /// and `errors`. Returns nothing unless validation is enabled.
+    fn impl_validate(&self, name: &syn::Ident, body: TokenStream) -> Option<TokenStream> {
+        if !self.validate {
+            return None;
+        }
+        let validate = self.validate_path();
+        Some(quote! {
+            impl #validate::Validate for #name {
+                #[allow(unused_variables)]
+                fn validate_at(&self, pointer: &str, errors: &mut #validate::ValidationErrors) {
+                    #body
+                }
+            }
+            impl #validate::Constrained for #name {}
+        })
+    }
+
+    /// Returns the `#[serde(try_from = ...)]` attribute for the struct `name` along with the
+    /// `...Unchecked` struct it is deserialized through before being validated.
+    fn expand_unchecked(
+        &self,
+        name: &syn::Ident,
+        attributes: &Option<TokenStream>,
+        fields: &[TokenStream],
+        idents: &[syn::Ident],
+    ) -> (TokenStream, TokenStream) {
+        let validate = self.validate_path();
+        let unchecked = syn::Ident::new(&format!("{}Unchecked", name), Span::call_site());
+        let unchecked_name = unchecked.to_string();
+        let try_from = quote! { #[serde(try_from = #unchecked_name)] };
+        let unchecked = quote! {
+            #[doc(hidden)]
+            #[derive(Deserialize)]
+            #attributes
+            pub struct #unchecked {
+                #(#fields),*
+            }
+            impl ::std::convert::TryFrom<#unchecked> for #name {
+                type Error = #validate::ValidationErrors;
+                fn try_from(unchecked: #unchecked) -> Result<Self, Self::Error> {
+                    let value = #name {
+                        #(#idents: unchecked.#idents),*
+                    };
+                    #validate::Validate::validate(&value)?;
+                    Ok(value)
+                }
+            }
+        };
+        (try_from, unchecked)
+    }
+
+    /// Returns a newtype `name` around `typ` which can only hold values satisfying
+    /// `constraints`.
+    fn expand_newtype(&self, name: &syn::Ident, typ: &str, constraints: TokenStream) -> TokenStream {
+        let validate = self.validate_path();
+        let inner = typ.parse::<TokenStream>().unwrap();
+        quote! {
+            #[derive(Clone, PartialEq, Debug, Deserialize, Serialize)]
+            #[serde(try_from = #typ, into = #typ)]
+            pub struct #name(#inner);
+            impl #name {
+                /// Checks that `value` satisfies the constraints of the schema.
+                pub fn new(value: #inner) -> Result<Self, #validate::ValidationErrors> {
+                    let value = #name(value);
+                    #validate::Validate::validate(&value)?;
+                    Ok(value)
+                }
+                pub fn into_inner(self) -> #inner {
+                    self.0
                 }
-                None => type_decl,
+            }
+            impl ::std::convert::TryFrom<#inner> for #name {
+                type Error = #validate::ValidationErrors;
+                fn try_from(value: #inner) -> Result<Self, Self::Error> {
+                    #name::new(value)
+                }
+            }
+            impl From<#name> for #inner {
+                fn from(value: #name) -> Self {
+                    value.0
+                }
+            }
+            impl ::std::ops::Deref for #name {
+                type Target = #inner;
+                fn deref(&self) -> &#inner {
+                    &self.0
+                }
+            }
+            impl #validate::Validate for #name {
+                fn validate_at(&self, pointer: &str, errors: &mut #validate::ValidationErrors) {
+                    #constraints.check(&self.0, pointer, errors);
+                    #validate::Validate::validate_at(&self.0, pointer, errors);
+                }
+            }
+            impl #validate::Constrained for #name {
+                fn number(&self) -> Option<f64> {
+                    #validate::Constrained::number(&self.0)
+                }
+                fn string(&self) -> Option<&str> {
+                    #validate::Constrained::string(&self.0)
+                }
+                fn items(&self, f: &mut dyn FnMut(usize, &dyn #validate::Constrained)) {
+                    #validate::Constrained::items(&self.0, f)
+                }
+                fn count(&self) -> Option<usize> {
+                    #validate::Constrained::count(&self.0)
+                }
+                fn unique(&self) -> Option<bool> {
+                    #validate::Constrained::unique(&self.0)
+                }
+            }
+        }
+    }
+
+    /// Returns the statements validating the field `field_name` described by `schema`.
+    fn validate_field(&self, field_name: &str, schema: &Schema) -> Result<TokenStream, Error> {
+        let validate = self.validate_path();
+        let field = field_ident(field_name);
+        let check = self.check_constraints(schema, quote!(&self.#field))?;
+        Ok(quote! {
+            {
+                let pointer = &#validate::child(pointer, #field_name);
+                #check
+                #validate::Validate::validate_at(&self.#field, pointer, errors);
+            }
+        })
+    }
+
+    /// Returns a statement checking the constraint keywords of `schema` against `value`.
+    fn check_constraints(&self, schema: &Schema, value: TokenStream) -> Result<TokenStream, Error> {
+        Ok(match self.constraints(schema)? {
+            Some(constraints) => quote! {
+                #constraints.check(#value, pointer, errors);
+            },
+            None => quote!(),
+        })
+    }
+
+    /// Returns a `Constraints` expression for the constraint keywords of `schema`, if it has
+    /// any.
+    fn constraints(&self, schema: &Schema) -> Result<Option<TokenStream>, Error> {
+        let schema = self.schema(schema)?;
+        let mut constraints = Vec::new();
+
+        // In draft 4 `exclusiveMinimum` and `exclusiveMaximum` make `minimum` and `maximum`
+        // exclusive
+        let (mut minimum, mut maximum) = (schema.minimum, schema.maximum);
+        let exclusive = |limit: &Option<ExclusiveLimit>, bound: &mut Option<f64>| match *limit {
+            Some(ExclusiveLimit::Number(limit)) => Some(limit),
+            Some(ExclusiveLimit::Boolean(true)) => bound.take(),
+            _ => None,
+        };
+        let exclusive_minimum = exclusive(&schema.exclusive_minimum, &mut minimum);
+        let exclusive_maximum = exclusive(&schema.exclusive_maximum, &mut maximum);
+        let numbers = [
+            ("minimum", minimum),
+            ("maximum", maximum),
+            ("exclusive_minimum", exclusive_minimum),
+            ("exclusive_maximum", exclusive_maximum),
+            ("multiple_of", schema.multiple_of),
+        ];
+        for (keyword, value) in numbers.iter() {
+            if let Some(value) = value {
+                let keyword = syn::Ident::new(keyword, Span::call_site());
+                constraints.push(quote! { #keyword: Some(#value) });
+            }
+        }
+
+        let counts = [
+            ("min_length", schema.min_length.and_then(|n| u64::try_from(n).ok())),
+            ("max_length", schema.max_length.and_then(|n| u64::try_from(n).ok())),
+            ("min_items", schema.min_items.and_then(|n| u64::try_from(n).ok())),
+            ("max_items", schema.max_items.and_then(|n| u64::try_from(n).ok())),
+        ];
+        for (keyword, value) in counts.iter() {
+            if let Some(value) = value {
+                let keyword = syn::Ident::new(keyword, Span::call_site());
+                constraints.push(quote! { #keyword: Some(#value) });
+            }
+        }
+
+        if let Some(ref pattern) = schema.pattern {
+            // The `regex` crate does not support every pattern, such as those with look-around
+            if let Err(err) = regex::Regex::new(pattern) {
+                return Err(self.error(format!("Unsupported pattern {:?}: {}", pattern, err)));
+            }
+            constraints.push(quote! { pattern: Some(#pattern) });
+        }
+        if schema.unique_items == Some(true) {
+            constraints.push(quote! { unique_items: true });
+        }
+        // Items given by position are not checked
+        if let (None, Some(item)) = (schema.positional_items(), schema.items_schema()) {
+            if let Some(items) = self.constraints(item)? {
+                constraints.push(quote! { items: Some(Box::new(#items)) });
+            }
+        }
+        // The parts of an `allOf` which could not be merged, such as a second `pattern`
+        let mut all_of = Vec::new();
+        for part in schema.all_of.iter().flatten() {
+            all_of.extend(self.constraints(part)?);
+        }
+        if !all_of.is_empty() {
+            constraints.push(quote! { all_of: vec![#(#all_of),*] });
+        }
+
+        if constraints.is_empty() {
+            return Ok(None);
+        }
+        let validate = self.validate_path();
+        Ok(Some(quote! {
+            #validate::Constraints {
+                #(#constraints,)*
+                ..::std::default::Default::default()
+            }
+        }))
+    }
+
+    fn expand_definitions(&mut self, schema: &Schema) -> Result<(), Error> {
+        let definitions = schema.definitions.iter().map(|def| ("definitions", def));
+        let defs = schema._defs.iter().map(|def| ("$defs", def));
+        for (keyword, (name, def)) in definitions.chain(defs) {
+            self.at(&[keyword, name], |expander| {
+                let type_decl = expander.expand_schema(name, def)?;
+                let definition_tokens = match def.description {
+                    Some(ref comment) => {
+                        let t = make_doc_comment(comment, LINE_LENGTH);
+                        quote! {
+                            #t
+                            #type_decl
+                        }
+                    }
+                    None => type_decl,
+                };
+                expander.push_type(name.to_string(), definition_tokens);
+                Ok(())
+            })?;
+        }
+        Ok(())
+    }
+
+    /// Generates the types which are referenced from other documents or which are referenced
+    /// within the root document but are not definitions (and would not be generated otherwise).
+    fn expand_refs(&mut self) -> Result<(), Error> {
+        let mut local_refs = Vec::new();
+        for ref_ in store::refs(self.root) {
+            let fragment = store::split_ref(&ref_).1;
+            let generate = if fragment.is_empty() {
+                // Schemas identified by an `id` which are not the root
+                !std::ptr::eq(self.document(&ref_)?.0, self.root)
+            } else {
+                anchor(fragment).is_none() && !pointer::is_definition(fragment)
             };
-            self.types.push((name.to_string(), definition_tokens));
+            if generate {
+                local_refs.push(ref_);
+            }
+        }
+        let external_refs = self
+            .store
+            .into_iter()
+            .flat_map(|store| store.external_refs())
+            .map(String::from)
+            .collect::<Vec<_>>();
+        for ref_ in local_refs.into_iter().chain(external_refs) {
+            let name = self.type_ref(&ref_)?;
+            let (document, pointer) = self.location_of(&ref_)?;
+            if self
+                .types
+                .iter()
+                .any(|t| type_name(&t.0) == name && t.1 == (document.clone(), pointer.clone()))
+            {
+                continue;
+            }
+            let def = self.schema_ref(&ref_)?;
+
+            let document = std::mem::replace(&mut self.document, document);
+            let location = std::mem::replace(
+                &mut self.location,
+                pointer::tokens(&pointer).unwrap_or_default(),
+            );
+            let expanded = self.expand_schema(&name, &def).map(|type_decl| {
+                let definition_tokens = match def.description {
+                    Some(ref comment) => {
+                        let t = make_doc_comment(comment, LINE_LENGTH);
+                        quote! {
+                            #t
+                            #type_decl
+                        }
+                    }
+                    None => type_decl,
+                };
+                self.push_type(name, definition_tokens);
+            });
+            self.document = document;
+            self.location = location;
+            expanded?;
         }
+        Ok(())
     }
 
-    fn expand_schema(&mut self, original_name: &str, schema: &Schema) -> TokenStream {
-        self.expand_definitions(schema);
+    fn expand_schema(&mut self, original_name: &str, schema: &Schema) -> Result<TokenStream, Error> {
+        self.expand_definitions(schema)?;
 
-        let pascal_case_name = replace_invalid_identifier_chars(&original_name.to_pascal_case());
+        let const_schema;
+        let schema = match schema.const_ {
+            Some(ref value @ Value::String(_))
+                if self.draft >= Draft::Draft6 && schema.enum_.is_none() =>
+            {
+                const_schema = Schema {
+                    enum_: Some(vec![value.clone()]),
+                    ..schema.clone()
+                };
+                &const_schema
+            }
+            _ => schema,
+        };
+
+        let pascal_case_name = type_name(original_name);
         self.current_type.clone_from(&pascal_case_name);
-        let (fields, default) = {
+        let base = self.inherited_base(schema)?;
+        let merged;
+        let schema = if base.is_none() && schema._ref.is_none() && schema.all_of.is_some() {
+            merged = {
+                let expander: &Expander<'_> = self;
+                expander.schema(schema)?.into_owned()
+            };
+            &merged
+        } else {
+            schema
+        };
+        let (mut fields, default, mut validations, mut idents) = {
             let mut field_expander = FieldExpander {
                 default: true,
+                validations: Vec::new(),
+                idents: Vec::new(),
                 expander: self,
             };
-            let fields = field_expander.expand_fields(original_name, schema);
-            (fields, field_expander.default)
+            let own = base.as_ref().map_or(schema, |(_, own)| own);
+            let fields = field_expander.expand_fields(original_name, own)?;
+            (
+                fields,
+                field_expander.default,
+                field_expander.validations,
+                field_expander.idents,
+            )
+        };
+        let name = str_to_ident(&pascal_case_name);
+        let rename = if name == original_name {
+            None
+        } else {
+            Some(quote! { #[serde(rename = #original_name)] })
         };
-        let name = syn::Ident::new(&pascal_case_name, Span::call_site());
-        let is_struct =
-            !fields.is_empty() || schema.additional_properties == Some(Value::Bool(false));
+        let closed = is_closed(schema);
+        // The base is flattened into the struct, which serde does not allow to deny unknown fields
+        let (closed, default, deref) = match base {
+            Some((base, _)) => {
+                let mut field = String::from("base");
+                while idents.iter().any(|ident| *ident == field) {
+                    field.push('_');
+                }
+                let field = syn::Ident::new(&field, Span::call_site());
+                let base = base.parse::<TokenStream>().unwrap();
+                if self.validate {
+                    let validate = self.validate_path();
+                    validations.push(quote! {
+                        #validate::Validate::validate_at(&self.#field, pointer, errors);
+                    });
+                }
+                fields.insert(
+                    0,
+                    quote! {
+                        #[serde(flatten)]
+                        pub #field : #base
+                    },
+                );
+                idents.push(field.clone());
+                let deref = quote! {
+                    impl ::std::ops::Deref for #name {
+                        type Target = #base;
+                        fn deref(&self) -> &#base {
+                            &self.#field
+                        }
+                    }
+                };
+                (false, false, Some(deref))
+            }
+            None => (closed, default, None),
+        };
+        let is_struct = !fields.is_empty() || closed;
         let type_decl = if is_struct {
+            let validation = self.impl_validate(&name, quote! { #(#validations)* });
+            let deny_unknown_fields = if closed {
+                Some(quote! { #[serde(deny_unknown_fields)] })
+            } else {
+                None
+            };
+            let (try_from, unchecked) = if self.validate_on_deserialize {
+                let (try_from, unchecked) =
+                    self.expand_unchecked(&name, &deny_unknown_fields, &fields, &idents);
+                (Some(try_from), Some(unchecked))
+            } else {
+                (None, None)
+            };
             if default {
                 quote! {
                     #[derive(Clone, PartialEq, Debug, Default, Deserialize, Serialize)]
+                    #try_from
+                    #deny_unknown_fields
+                    #rename
                     pub struct #name {
                         #(#fields),*
                     }
+                    #deref
+                    #validation
+                    #unchecked
                 }
             } else {
                 quote! {
                     #[derive(Clone, PartialEq, Debug, Deserialize, Serialize)]
+                    #try_from
+                    #deny_unknown_fields
+                    #rename
                     pub struct #name {
                         #(#fields),*
                     }
+                    #deref
+                    #validation
+                    #unchecked
                 }
             }
         } else if schema.enum_.as_ref().map_or(false, |e| !e.is_empty()) {
@@ -511,144 +2065,206 @@ impl<'r> Expander<'r> {
                 let values = schema.enum_.as_ref().map_or(&[][..], |v| v);
                 let names = schema.enum_names.as_ref().map_or(&[][..], |v| v);
                 if names.len() != values.len() {
-                    panic!("enumNames(length {}) and enum(length {}) have different length", names.len(), values.len())
+                    return Err(self.error(format!(
+                        "enumNames(length {}) and enum(length {}) have different length",
+                        names.len(),
+                        values.len()
+                    )));
                 }
                 names.iter()
                     .enumerate()
                     .map(|(idx, name)| (&values[idx], name))
-                    .flat_map(|(value, name)| {
-                        let pascal_case_variant = name.to_pascal_case();
-                        let variant_name =
-                            rename_keyword("", &pascal_case_variant).unwrap_or_else(|| {
-                                let v = syn::Ident::new(&pascal_case_variant, Span::call_site());
-                                quote!(#v)
-                            });
+                    .filter_map(|(value, name)| {
+                        let variant_name = str_to_ident(&type_name(name));
                         match value {
-                            Value::String(ref s) => Some(quote! {
+                            Value::String(ref s) => Some(Ok(quote! {
                                 #[serde(rename = #s)]
                                 #variant_name
-                            }),
+                            })),
                             Value::Number(ref n) => {
                                 repr_i64 = true;
                                 let num = syn::LitInt::new(&n.to_string(), Span::call_site());
-                                Some(quote! {
+                                Some(Ok(quote! {
                                     #variant_name = #num
-                                })
+                                }))
                             },
                             Value::Null => {
                                 optional = true;
                                 None
                             },
-                            _ => panic!("Expected string,bool or number for enum got `{}`", value),
+                            _ => Some(Err(self.error(format!("Expected string or number for enum got `{}`", value)))),
                         }
                     })
-                    .collect::<Vec<_>>()
+                    .collect::<Result<Vec<_>, Error>>()?
             } else {
                 schema
                     .enum_
                     .as_ref()
                     .map_or(&[][..], |v| v)
                     .iter()
-                    .flat_map(|v| match *v {
+                    .filter_map(|v| match *v {
                         Value::String(ref v) => {
-                            let pascal_case_variant = v.to_pascal_case();
-                            let variant_name =
-                                rename_keyword("", &pascal_case_variant).unwrap_or_else(|| {
-                                    let v = syn::Ident::new(&pascal_case_variant, Span::call_site());
-                                    quote!(#v)
-                                });
-                            Some(if pascal_case_variant == *v {
-                                variant_name
+                            let variant_name = str_to_ident(&type_name(v));
+                            Some(Ok(if variant_name == v {
+                                quote!(#variant_name)
                             } else {
                                 quote! {
                                     #[serde(rename = #v)]
                                     #variant_name
                                 }
-                            })
+                            }))
                         }
                         Value::Null => {
                             optional = true;
                             None
                         }
-                        _ => panic!("Expected string for enum got `{}`", v),
+                        _ => Some(Err(self.error(format!("Expected string for enum got `{}`", v)))),
                     })
-                    .collect::<Vec<_>>()
+                    .collect::<Result<Vec<_>, Error>>()?
             };
             if optional {
                 let enum_name = syn::Ident::new(&format!("{}_", name), Span::call_site());
+                let validation = self.impl_validate(&enum_name, quote!());
                 if repr_i64 {
                     quote! {
                         pub type #name = Option<#enum_name>;
-                        #[derive(Clone, PartialEq, Debug, Serialize_repr, Deserialize_repr)]
+                        #[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Serialize_repr, Deserialize_repr)]
                         #[repr(i64)]
                         pub enum #enum_name {
                             #(#variants),*
                         }
+                        #validation
                     }
                 } else {
                     quote! {
                         pub type #name = Option<#enum_name>;
-                        #[derive(Clone, PartialEq, Debug, Deserialize, Serialize)]
+                        #[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Deserialize, Serialize)]
                         pub enum #enum_name {
                             #(#variants),*
                         }
+                        #validation
                     }
                 }
             } else {
+                let validation = self.impl_validate(&name, quote!());
                 if repr_i64 {
                     quote! {
-                        #[derive(Clone, PartialEq, Debug, Serialize_repr, Deserialize_repr)]
+                        #[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Serialize_repr, Deserialize_repr)]
                         #[repr(i64)]
+                        #rename
                         pub enum #name {
                             #(#variants),*
                         }
+                        #validation
                     }
                 } else {
                     quote! {
-                        #[derive(Clone, PartialEq, Debug, Deserialize, Serialize)]
+                        #[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Deserialize, Serialize)]
+                        #rename
                         pub enum #name {
                             #(#variants),*
                         }
+                        #validation
                     }
                 }
             }
+        } else if let Some((keyword, (alternatives, nullable))) = union_alternatives(schema)
+            .filter(|_| schema._ref.is_none())
+            .map(|(keyword, alternatives)| (keyword, split_null_alternative(alternatives)))
+            .filter(|(_, (alternatives, _))| alternatives.len() > 1)
+        {
+            if nullable {
+                let enum_name = format!("{}_", pascal_case_name);
+                let union = self.at(&[keyword], |expander| {
+                    expander.expand_union(&enum_name, &alternatives)
+                })?;
+                let enum_name = syn::Ident::new(&enum_name, Span::call_site());
+                quote! {
+                    pub type #name = Option<#enum_name>;
+                    #union
+                }
+            } else {
+                self.at(&[keyword], |expander| {
+                    expander.expand_union(&pascal_case_name, &alternatives)
+                })?
+            }
         } else {
-            let typ = self
-                .expand_type("", true, schema)
-                .typ
-                .parse::<TokenStream>()
-                .unwrap();
-            return quote! {
+            let typ = self.expand_type(&pascal_case_name, true, schema)?.typ;
+            if (self.newtypes || self.validate_on_deserialize) && schema._ref.is_none() {
+                if let Some(constraints) = self.constraints(schema)? {
+                    return Ok(self.expand_newtype(&name, &typ, constraints));
+                }
+            }
+            let typ = typ.parse::<TokenStream>().unwrap();
+            return Ok(quote! {
                 pub type #name = #typ;
-            };
+            });
         };
-        if name == original_name {
-            type_decl
+        Ok(type_decl)
+    }
+
+    pub fn expand(&mut self, schema: &Schema) -> Result<TokenStream, Error> {
+        // The references of the copy of the root kept by the store are resolved within the scopes
+        // of their `id`s
+        let schema = if std::ptr::eq(schema, self.given_root) {
+            self.root
         } else {
-            quote! {
-                #[serde(rename = #original_name)]
-                #type_decl
+            schema
+        };
+        // Types may hold each other, so the first pass only finds out which references must be
+        // boxed to break the cycles
+        self.held = Some(Vec::new());
+        self.expand_types(schema)?;
+        self.boxed = cycles::recursive_edges(&self.held.take().unwrap_or_default());
+        self.types.clear();
+        self.expand_types(schema)?;
+
+        // Types which are reached through several documents are only generated once, but two
+        // schemas must not be generated as the same type
+        let mut locations = BTreeMap::new();
+        let mut types = Vec::new();
+        for (name, location, tokens) in &self.types {
+            match locations.entry(type_name(name)) {
+                Entry::Vacant(entry) => {
+                    entry.insert(location);
+                    types.push(tokens);
+                }
+                Entry::Occupied(entry) if *entry.get() == location => (),
+                Entry::Occupied(entry) => {
+                    let (document, pointer) = entry.get();
+                    return Err(Error::new(
+                        location.0.clone(),
+                        location.1.clone(),
+                        format!(
+                            "The type `{}` is also generated for the schema at `{}#{}`",
+                            entry.key(),
+                            document.as_deref().unwrap_or(""),
+                            pointer
+                        ),
+                    ));
+                }
             }
         }
+
+        Ok(quote! {
+            #( #types )*
+        })
     }
 
-    pub fn expand(&mut self, schema: &Schema) -> TokenStream {
+    fn expand_types(&mut self, schema: &Schema) -> Result<(), Error> {
+        self.current_type.clear();
+        self.current_field.clear();
         match self.root_name {
             Some(name) => {
-                let schema = self.expand_schema(name, schema);
-                self.types.push((name.to_string(), schema));
+                let schema = self.expand_schema(name, schema)?;
+                self.push_type(name.to_string(), schema);
             }
-            None => self.expand_definitions(schema),
-        }
-
-        let types = self.types.iter().map(|t| &t.1);
-
-        quote! {
-            #( #types )*
+            None => self.expand_definitions(schema)?,
         }
+        self.expand_refs()
     }
 
-    pub fn expand_root(&mut self) -> TokenStream {
+    pub fn expand_root(&mut self) -> Result<TokenStream, Error> {
         self.expand(self.root)
     }
 }
@@ -0,0 +1,146 @@
+//! [JSON Pointer](https://tools.ietf.org/html/rfc6901) resolution within schema documents.
+
+use std::borrow::Cow;
+
+use schemafy_core::uri::percent_decode;
+use serde_json::Value;
+
+use crate::{Items, Schema, SchemaDependencies};
+
+/// Joins the unescaped reference `tokens` into a JSON pointer.
+pub(crate) fn join(tokens: &[impl AsRef<str>]) -> String {
+    tokens
+        .iter()
+        .map(|token| format!("/{}", token.as_ref().replace('~', "~0").replace('/', "~1")))
+        .collect()
+}
+
+/// Splits the (URI fragment) pointer `fragment` into its unescaped reference tokens, returning
+/// `None` if `fragment` is not a JSON pointer.
+pub(crate) fn tokens(fragment: &str) -> Option<Vec<String>> {
+    let fragment = percent_decode(fragment);
+    if fragment.is_empty() {
+        return Some(Vec::new());
+    }
+    let tokens = fragment
+        .strip_prefix('/')?
+        .split('/')
+        .map(|token| token.replace("~1", "/").replace("~0", "~"))
+        .collect();
+    Some(tokens)
+}
+
+/// Returns the name of the type generated for the schema at `fragment`.
+///
+/// Schemas under `definitions` (or `$defs`) are named after their definition, anything else is
+/// named from its path below the closest enclosing definition.
+pub(crate) fn name(fragment: &str) -> String {
+    let tokens = tokens(fragment).unwrap_or_default();
+    let start = tokens
+        .iter()
+        .enumerate()
+        .rev()
+        .skip(1)
+        .find(|(_, token)| *token == "definitions" || *token == "$defs")
+        .map_or(0, |(i, _)| i + 1);
+    tokens[start..].join("_")
+}
+
+/// Returns true if `fragment` points directly at a definition of some schema.
+pub(crate) fn is_definition(fragment: &str) -> bool {
+    let tokens = tokens(fragment).unwrap_or_default();
+    tokens.len() >= 2 && matches!(&tokens[tokens.len() - 2][..], "definitions" | "$defs")
+}
+
+/// Resolves `fragment` within `schema`.
+///
+/// Schemas which the model only keeps as JSON values (such as `additionalProperties`) are
+/// deserialized from those values.
+pub(crate) fn resolve<'r>(mut schema: &'r Schema, fragment: &str) -> Option<Cow<'r, Schema>> {
+    let tokens = tokens(fragment)?;
+    let mut i = 0;
+    while i < tokens.len() {
+        let token = &tokens[i][..];
+        let next = tokens.get(i + 1).map(|token| &token[..]);
+        i += 2;
+        schema = match token {
+            "definitions" => schema.definitions.get(next?)?,
+            "$defs" => schema._defs.get(next?)?,
+            "properties" => schema.properties.get(next?)?,
+            "patternProperties" => schema.pattern_properties.get(next?)?,
+            "dependentSchemas" => schema.dependent_schemas.get(next?)?,
+            "allOf" => schema.all_of.as_ref()?.get(index(next?)?)?,
+            "anyOf" => schema.any_of.as_ref()?.get(index(next?)?)?,
+            "oneOf" => schema.one_of.as_ref()?.get(index(next?)?)?,
+            "prefixItems" => schema.prefix_items.as_ref()?.get(index(next?)?)?,
+            // `items` is either a single schema or an array of schemas
+            "items" => match *schema.items.as_deref()? {
+                Items::SchemaArray(ref items) => items.get(index(next?)?)?,
+                Items::Schema(ref items) => {
+                    i -= 1;
+                    items
+                }
+            },
+            "dependencies" => match *schema.dependencies.as_ref()?.get(next?)? {
+                SchemaDependencies::Schema(ref schema) => schema,
+                SchemaDependencies::StringArray(_) => return None,
+            },
+            _ => {
+                i -= 1;
+                match token {
+                    "additionalItems" => schema.additional_items.as_deref()?,
+                    "additionalProperties" => schema.additional_properties.as_deref()?,
+                    "contains" => schema.contains.as_deref()?,
+                    "else" => schema.else_.as_deref()?,
+                    "if" => schema.if_.as_deref()?,
+                    "not" => schema.not.as_deref()?,
+                    "propertyNames" => schema.property_names.as_deref()?,
+                    "then" => schema.then.as_deref()?,
+                    "unevaluatedItems" => schema.unevaluated_items.as_deref()?,
+                    "unevaluatedProperties" => schema.unevaluated_properties.as_deref()?,
+                    _ => return None,
+                }
+            }
+        };
+    }
+    Some(Cow::Borrowed(schema))
+}
+
+/// Resolves the reference `tokens` within the JSON `value` of a schema.
+pub(crate) fn resolve_value(value: &Value, tokens: &[String]) -> Option<Schema> {
+    let value = value.pointer(&join(tokens))?;
+    Schema::from_value(value.clone()).ok()
+}
+
+fn index(token: &str) -> Option<usize> {
+    if token.len() > 1 && token.starts_with('0') {
+        return None;
+    }
+    token.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unescape_tokens() {
+        assert_eq!(tokens(""), Some(Vec::new()));
+        assert_eq!(tokens("/definitions/a~1b").unwrap(), ["definitions", "a/b"]);
+        assert_eq!(tokens("/properties/~01").unwrap(), ["properties", "~1"]);
+        assert_eq!(
+            tokens("/definitions/with%20space").unwrap(),
+            ["definitions", "with space"]
+        );
+        assert_eq!(tokens("//foo").unwrap(), ["", "foo"]);
+        assert_eq!(tokens("definitions/a"), None);
+    }
+
+    #[test]
+    fn names() {
+        assert_eq!(name("/definitions/a"), "a");
+        assert_eq!(name("/$defs/a/$defs/b"), "b");
+        assert_eq!(name("/properties/foo/items"), "properties_foo_items");
+        assert_eq!(name("/definitions/a/allOf/0"), "a_allOf_0");
+    }
+}
@@ -0,0 +1,190 @@
+use std::{
+    collections::BTreeMap,
+    io,
+    path::{Component, Path, PathBuf},
+};
+
+/// Loads the schema documents which `$ref`s point into.
+///
+/// A resolver which does not know the document at a URI should return an error of kind
+/// [`io::ErrorKind::NotFound`] so that the next resolver in a list of resolvers is tried.
+pub trait SchemaResolver {
+    /// Returns the JSON text of the schema document at `uri`.
+    fn resolve(&self, uri: &str) -> io::Result<String>;
+}
+
+impl<R> SchemaResolver for &'_ R
+where
+    R: SchemaResolver + ?Sized,
+{
+    fn resolve(&self, uri: &str) -> io::Result<String> {
+        (**self).resolve(uri)
+    }
+}
+
+impl<R> SchemaResolver for Box<R>
+where
+    R: SchemaResolver + ?Sized,
+{
+    fn resolve(&self, uri: &str) -> io::Result<String> {
+        (**self).resolve(uri)
+    }
+}
+
+/// Tries each resolver in order, returning the first document which is found.
+impl<R> SchemaResolver for [R]
+where
+    R: SchemaResolver,
+{
+    fn resolve(&self, uri: &str) -> io::Result<String> {
+        for resolver in self {
+            match resolver.resolve(uri) {
+                Err(ref err) if err.kind() == io::ErrorKind::NotFound => (),
+                result => return result,
+            }
+        }
+        Err(not_found(uri))
+    }
+}
+
+impl<R> SchemaResolver for Vec<R>
+where
+    R: SchemaResolver,
+{
+    fn resolve(&self, uri: &str) -> io::Result<String> {
+        self[..].resolve(uri)
+    }
+}
+
+/// Reads documents from the filesystem, treating their URIs (with or without `file://`) as
+/// paths.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct FileResolver;
+
+impl SchemaResolver for FileResolver {
+    fn resolve(&self, uri: &str) -> io::Result<String> {
+        std::fs::read_to_string(uri.strip_prefix("file://").unwrap_or(uri))
+    }
+}
+
+/// Reads the documents whose URIs start with a prefix, such as `https://schemas.example.com/`,
+/// from a directory which mirrors them.
+#[derive(Clone, Debug)]
+pub struct DirectoryResolver {
+    prefix: String,
+    directory: PathBuf,
+}
+
+impl DirectoryResolver {
+    /// Maps the URIs starting with `prefix` to files in `directory`. If `prefix` is a complete
+    /// URI then `directory` may also be the file it maps to.
+    pub fn new(prefix: impl Into<String>, directory: impl AsRef<Path>) -> DirectoryResolver {
+        DirectoryResolver {
+            prefix: prefix.into(),
+            directory: directory.as_ref().to_path_buf(),
+        }
+    }
+
+    /// Returns the path of the file which the document at `uri` is read from, unless that file
+    /// would lie outside of the directory (through `..` or an absolute path).
+    pub fn path(&self, uri: &str) -> Option<PathBuf> {
+        let rest = uri.strip_prefix(&self.prefix[..])?;
+        let rest = Path::new(rest.trim_start_matches('/'));
+        if rest
+            .components()
+            .any(|component| !matches!(component, Component::Normal(_) | Component::CurDir))
+        {
+            return None;
+        }
+        if rest.as_os_str().is_empty() {
+            Some(self.directory.clone())
+        } else {
+            Some(self.directory.join(rest))
+        }
+    }
+}
+
+impl SchemaResolver for DirectoryResolver {
+    fn resolve(&self, uri: &str) -> io::Result<String> {
+        if !uri.starts_with(&self.prefix[..]) {
+            return Err(not_found(uri));
+        }
+        let path = self.path(uri).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "`{}` lies outside of `{}`",
+                    uri,
+                    self.directory.to_string_lossy()
+                ),
+            )
+        })?;
+        std::fs::read_to_string(path)
+    }
+}
+
+/// Serves documents from memory.
+#[derive(Clone, Debug, Default)]
+pub struct MemoryResolver {
+    documents: BTreeMap<String, String>,
+}
+
+impl MemoryResolver {
+    pub fn new() -> MemoryResolver {
+        MemoryResolver::default()
+    }
+
+    /// Adds the document at `uri` with the JSON text `json`.
+    pub fn insert(&mut self, uri: impl Into<String>, json: impl Into<String>) -> &mut Self {
+        self.documents.insert(uri.into(), json.into());
+        self
+    }
+}
+
+impl SchemaResolver for MemoryResolver {
+    fn resolve(&self, uri: &str) -> io::Result<String> {
+        self.documents
+            .get(uri)
+            .cloned()
+            .ok_or_else(|| not_found(uri))
+    }
+}
+
+fn not_found(uri: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::NotFound,
+        format!("No resolver for `{}`", uri),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn directory_paths() {
+        let resolver = DirectoryResolver::new("https://example.com/schemas/", "vendor");
+        assert_eq!(
+            resolver.path("https://example.com/schemas/a/b.json"),
+            Some(Path::new("vendor").join("a/b.json"))
+        );
+        assert_eq!(resolver.path("https://example.com/other.json"), None);
+        assert_eq!(
+            resolver.path("https://example.com/schemas/../secret.json"),
+            None
+        );
+        assert_eq!(
+            resolver.path("https://example.com/schemas/a/../../secret.json"),
+            None
+        );
+
+        let err = resolver
+            .resolve("https://example.com/schemas/../secret.json")
+            .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        let err = resolver
+            .resolve("https://example.com/other.json")
+            .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::NotFound);
+    }
+}
@@ -1,8 +1,24 @@
+/// `exclusiveMaximum` and `exclusiveMinimum` modify `maximum` and `minimum` in draft 4 but are
+/// limits of their own since draft 6.
+#[derive(Clone, PartialEq, Debug, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum ExclusiveLimit {
+    Boolean(bool),
+    Number(f64),
+}
+/// A schema for every item or, before draft 2020-12, an array of schemas for the items at the same
+/// positions.
+#[derive(Clone, PartialEq, Debug, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum Items {
+    Schema(Schema),
+    SchemaArray(SchemaArray),
+}
 pub type PositiveInteger = i64;
-pub type PositiveIntegerDefault0 = serde_json::Value;
+pub type PositiveIntegerDefault0 = i64;
 pub type SchemaArray = Vec<Schema>;
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Deserialize, Serialize)]
 #[serde(rename = "simpleTypes")]
-#[derive(Clone, PartialEq, Debug, Deserialize, Serialize)]
 pub enum SimpleTypes {
     #[serde(rename = "array")]
     Array,
@@ -21,36 +37,67 @@ pub enum SimpleTypes {
 }
 pub type StringArray = Vec<String>;
 #[derive(Clone, PartialEq, Debug, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum SchemaDependencies {
+    StringArray(StringArray),
+    Schema(Schema),
+}
+#[derive(Clone, PartialEq, Debug, Deserialize, Serialize)]
 pub struct Schema {
+    #[serde(rename = "$anchor")]
+    pub _anchor: Option<String>,
+    #[serde(rename = "$comment")]
+    pub _comment: Option<String>,
+    #[serde(default)]
+    #[serde(rename = "$defs")]
+    pub _defs: ::std::collections::BTreeMap<String, Schema>,
+    #[serde(rename = "$id")]
+    pub _id: Option<String>,
     #[serde(rename = "$ref")]
-    pub ref_: Option<String>,
+    pub _ref: Option<String>,
     #[serde(rename = "$schema")]
-    pub schema: Option<String>,
+    pub _schema: Option<String>,
     #[serde(rename = "additionalItems")]
-    pub additional_items: Option<serde_json::Value>,
+    pub additional_items: Option<Box<Schema>>,
     #[serde(rename = "additionalProperties")]
-    pub additional_properties: Option<serde_json::Value>,
+    pub additional_properties: Option<Box<Schema>>,
     #[serde(rename = "allOf")]
     pub all_of: Option<SchemaArray>,
     #[serde(rename = "anyOf")]
     pub any_of: Option<SchemaArray>,
+    #[serde(rename = "const")]
+    pub const_: Option<serde_json::Value>,
+    pub contains: Option<Box<Schema>>,
+    #[serde(rename = "contentEncoding")]
+    pub content_encoding: Option<String>,
+    #[serde(rename = "contentMediaType")]
+    pub content_media_type: Option<String>,
     pub default: Option<serde_json::Value>,
     #[serde(default)]
     pub definitions: ::std::collections::BTreeMap<String, Schema>,
-    pub dependencies: Option<::std::collections::BTreeMap<String, serde_json::Value>>,
+    pub dependencies: Option<::std::collections::BTreeMap<String, SchemaDependencies>>,
+    #[serde(rename = "dependentRequired")]
+    pub dependent_required: Option<::std::collections::BTreeMap<String, StringArray>>,
+    #[serde(default)]
+    #[serde(rename = "dependentSchemas")]
+    pub dependent_schemas: ::std::collections::BTreeMap<String, Schema>,
     pub description: Option<String>,
+    #[serde(rename = "else")]
+    pub else_: Option<Box<Schema>>,
     #[serde(rename = "enum")]
     pub enum_: Option<Vec<serde_json::Value>>,
     #[serde(rename = "enumNames")]
     pub enum_names: Option<Vec<String>>,
+    pub examples: Option<Vec<serde_json::Value>>,
     #[serde(rename = "exclusiveMaximum")]
-    pub exclusive_maximum: Option<bool>,
+    pub exclusive_maximum: Option<ExclusiveLimit>,
     #[serde(rename = "exclusiveMinimum")]
-    pub exclusive_minimum: Option<bool>,
+    pub exclusive_minimum: Option<ExclusiveLimit>,
+    pub format: Option<String>,
     pub id: Option<String>,
-    #[serde(default)]
-    #[serde(with = "::schemafy_core::one_or_many")]
-    pub items: Vec<Schema>,
+    #[serde(rename = "if")]
+    pub if_: Option<Box<Schema>>,
+    pub items: Option<Box<Items>>,
     #[serde(rename = "maxItems")]
     pub max_items: Option<PositiveInteger>,
     #[serde(rename = "maxLength")]
@@ -74,14 +121,27 @@ pub struct Schema {
     #[serde(default)]
     #[serde(rename = "patternProperties")]
     pub pattern_properties: ::std::collections::BTreeMap<String, Schema>,
+    #[serde(rename = "prefixItems")]
+    pub prefix_items: Option<SchemaArray>,
     #[serde(default)]
     pub properties: ::std::collections::BTreeMap<String, Schema>,
+    #[serde(rename = "propertyNames")]
+    pub property_names: Option<Box<Schema>>,
+    #[serde(rename = "readOnly")]
+    pub read_only: Option<bool>,
     pub required: Option<StringArray>,
+    pub then: Option<Box<Schema>>,
     pub title: Option<String>,
     #[serde(default)]
     #[serde(with = "::schemafy_core::one_or_many")]
     #[serde(rename = "type")]
     pub type_: Vec<SimpleTypes>,
+    #[serde(rename = "unevaluatedItems")]
+    pub unevaluated_items: Option<Box<Schema>>,
+    #[serde(rename = "unevaluatedProperties")]
+    pub unevaluated_properties: Option<Box<Schema>>,
     #[serde(rename = "uniqueItems")]
     pub unique_items: Option<bool>,
+    #[serde(rename = "writeOnly")]
+    pub write_only: Option<bool>,
 }
@@ -0,0 +1,173 @@
+//! Hand-written additions to the [`Schema`] generated from `schema.json`.
+//!
+//! Since draft 6 `true` and `false` are schemas as well, which the generated types do not accept.
+//! They are replaced by the equivalent objects before deserializing: `true` accepts every
+//! instance like `{}` and `false` accepts none like `{ "not": {} }`.
+
+use std::str::FromStr;
+
+use serde_json::{Map, Value};
+
+use crate::{Items, Schema};
+
+/// The keywords whose value is a schema.
+const SCHEMA_KEYWORDS: &[&str] = &[
+    "additionalItems",
+    "additionalProperties",
+    "contains",
+    "else",
+    "if",
+    "items",
+    "not",
+    "propertyNames",
+    "then",
+    "unevaluatedItems",
+    "unevaluatedProperties",
+];
+
+/// The keywords whose value is an array of schemas.
+const ARRAY_KEYWORDS: &[&str] = &["allOf", "anyOf", "items", "oneOf", "prefixItems"];
+
+/// The keywords whose value maps names to schemas. The string arrays of `dependencies` are left
+/// as they are.
+const MAP_KEYWORDS: &[&str] = &[
+    "$defs",
+    "definitions",
+    "dependencies",
+    "dependentSchemas",
+    "patternProperties",
+    "properties",
+];
+
+impl Schema {
+    /// Deserializes a schema from a JSON value, accepting `true` and `false` as schemas.
+    pub fn from_value(mut value: Value) -> Result<Schema, serde_json::Error> {
+        visit_schemas(&mut value, &mut |schema| match *schema {
+            Value::Bool(true) => *schema = Value::Object(Map::new()),
+            Value::Bool(false) => *schema = serde_json::json!({ "not": {} }),
+            _ => (),
+        });
+        serde_json::from_value(value)
+    }
+
+    /// Returns the schema of every item, unless `items` lists the items by position.
+    pub(crate) fn items_schema(&self) -> Option<&Schema> {
+        match self.items.as_deref() {
+            Some(Items::Schema(items)) => Some(items),
+            _ => None,
+        }
+    }
+
+    /// Returns the schemas of the items given by position, in `prefixItems` or, before draft
+    /// 2020-12, in an array of `items`.
+    pub(crate) fn positional_items(&self) -> Option<&[Schema]> {
+        match (&self.prefix_items, self.items.as_deref()) {
+            (Some(prefix_items), _) => Some(prefix_items),
+            (None, Some(Items::SchemaArray(items))) => Some(items),
+            (None, _) => None,
+        }
+    }
+
+    /// Serializes the schema into a JSON value which leaves out the absent keywords instead of
+    /// setting them to `null` or to empty collections.
+    pub(crate) fn to_value(&self) -> Value {
+        let mut value = serde_json::to_value(self).expect("Schemas can be serialized");
+        visit_schemas(&mut value, &mut |schema| {
+            if let Value::Object(ref mut object) = *schema {
+                object.retain(|keyword, value| match *value {
+                    Value::Null => false,
+                    Value::Array(ref array) => !array.is_empty() || keyword != "type",
+                    Value::Object(ref object) => {
+                        !object.is_empty()
+                            || !MAP_KEYWORDS.contains(&&keyword[..])
+                            || keyword == "dependencies"
+                    }
+                    _ => true,
+                });
+            }
+        });
+        value
+    }
+}
+
+impl Default for Schema {
+    fn default() -> Schema {
+        serde_json::from_value(Value::Object(Map::new())).expect("Every keyword is optional")
+    }
+}
+
+impl FromStr for Schema {
+    type Err = serde_json::Error;
+
+    fn from_str(json: &str) -> Result<Schema, serde_json::Error> {
+        Schema::from_value(serde_json::from_str(json)?).map_err(|err| {
+            // Errors of values have no position, which deserializing the text directly reports
+            // unless it fails on a boolean schema first
+            match serde_json::from_str::<Schema>(json) {
+                Err(positioned) if positioned.to_string().starts_with(&err.to_string()) => {
+                    positioned
+                }
+                _ => err,
+            }
+        })
+    }
+}
+
+/// Calls `f` with `schema` and then with each of its subschemas, depth first.
+fn visit_schemas(schema: &mut Value, f: &mut impl FnMut(&mut Value)) {
+    f(schema);
+    if let Value::Object(ref mut object) = *schema {
+        for (keyword, value) in object {
+            let keyword = &keyword[..];
+            match *value {
+                Value::Array(ref mut schemas) if ARRAY_KEYWORDS.contains(&keyword) => {
+                    schemas
+                        .iter_mut()
+                        .for_each(|schema| visit_schemas(schema, f));
+                }
+                Value::Object(ref mut schemas) if MAP_KEYWORDS.contains(&keyword) => schemas
+                    .values_mut()
+                    .filter(|schema| !schema.is_array())
+                    .for_each(|schema| visit_schemas(schema, f)),
+                _ if SCHEMA_KEYWORDS.contains(&keyword) => visit_schemas(value, f),
+                _ => (),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn boolean_schemas() {
+        let schema: Schema = r#"{
+            "properties": { "a": true, "b": false },
+            "items": [true, { "not": false }],
+            "dependencies": { "a": ["b"], "b": false }
+        }"#
+        .parse()
+        .unwrap();
+        assert_eq!(schema.properties["a"], Schema::default());
+        assert!(crate::is_false(&schema.properties["b"]));
+        assert_eq!(
+            schema.to_value(),
+            serde_json::json!({
+                "properties": { "a": {}, "b": { "not": {} } },
+                "items": [{}, { "not": { "not": {} } }],
+                "dependencies": { "a": ["b"], "b": { "not": {} } }
+            })
+        );
+    }
+
+    #[test]
+    fn error_positions() {
+        let err = "{\n  \"type\": 5\n}".parse::<Schema>().unwrap_err();
+        assert_eq!((err.line(), err.column()), (2, 11));
+        let err = "{\n  \"items\": false,\n  \"type\": 5\n}"
+            .parse::<Schema>()
+            .unwrap_err();
+        assert_eq!(err.line(), 0);
+    }
+}
@@ -0,0 +1,292 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    io,
+    path::Path,
+};
+
+use serde_json::Value;
+
+pub(crate) use schemafy_core::uri::{resolve_uri, split_ref};
+use schemafy_core::{
+    uri::has_scheme,
+    validator::{SchemaError, Validator},
+};
+
+use crate::{resolver::FileResolver, Schema, SchemaResolver};
+
+/// The schema documents which are reachable from a root schema through `$ref`s into other files.
+///
+/// Each document is loaded once, relative to the location of the document which refers to it.
+/// References inside of the loaded documents (and of a copy of the root) are rewritten to be
+/// absolute, taking the base URIs set by `id`/`$id` into account, so they keep pointing at the
+/// right place when the schemas are used from another document.
+#[derive(Clone, Debug, Default)]
+pub struct SchemaStore {
+    root: String,
+    root_schema: Schema,
+    documents: BTreeMap<String, Schema>,
+    values: BTreeMap<String, Value>,
+    /// The document and JSON pointer of each schema identified by an `id`
+    ids: BTreeMap<String, (String, String)>,
+    refs: BTreeSet<String>,
+}
+
+impl SchemaStore {
+    /// Loads every document which `root`, located at `path`, refers to either directly or
+    /// through other documents from the filesystem.
+    pub fn load(path: impl AsRef<Path>, root: &Schema) -> io::Result<SchemaStore> {
+        SchemaStore::load_with(path, root, &FileResolver)
+    }
+
+    /// Loads every document which `root`, located at `path`, refers to either directly or
+    /// through other documents using `resolver`. `path` may also be a URI such as
+    /// `https://schemas.example.com/root.json`.
+    pub fn load_with(
+        path: impl AsRef<Path>,
+        root: &Schema,
+        resolver: &dyn SchemaResolver,
+    ) -> io::Result<SchemaStore> {
+        let path = path.as_ref();
+        let path = if path.is_relative() && !has_scheme(&path.to_string_lossy()) {
+            std::env::current_dir()?.join(path)
+        } else {
+            path.to_path_buf()
+        };
+        let mut store = SchemaStore {
+            // Without dot segments, as the references resolved against the root have none
+            root: resolve_uri("", &path.to_string_lossy().replace('\\', "/")),
+            ..SchemaStore::default()
+        };
+
+        let mut value = root.to_value();
+        let mut pending = store.absolutize(&store.root.clone(), &mut value);
+        store.root_schema = Schema::from_value(value.clone())?;
+        store.values.insert(store.root.clone(), value);
+        while let Some(uri) = pending.pop() {
+            if store.is_loaded(&uri) {
+                continue;
+            }
+            let json = resolver.resolve(&uri).map_err(|err| {
+                io::Error::new(err.kind(), format!("Unable to read `{}`: {}", uri, err))
+            })?;
+            let mut value = serde_json::from_str(&json).map_err(|err| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("Unable to parse `{}`: {}", uri, err),
+                )
+            })?;
+            pending.extend(store.absolutize(&uri, &mut value));
+            let schema = Schema::from_value(value.clone())?;
+            store.documents.insert(uri.clone(), schema);
+            store.values.insert(uri, value);
+        }
+        Ok(store)
+    }
+
+    /// The location of the root schema.
+    pub fn root(&self) -> &str {
+        &self.root
+    }
+
+    /// Returns the root schema with all of its references made absolute.
+    pub fn root_schema(&self) -> &Schema {
+        &self.root_schema
+    }
+
+    /// Returns the loaded document at `uri`.
+    pub fn document(&self, uri: &str) -> Option<&Schema> {
+        self.documents.get(uri)
+    }
+
+    /// Returns the location of every loaded document, not including the root.
+    pub fn documents(&self) -> impl Iterator<Item = &str> {
+        self.documents.keys().map(|uri| &uri[..])
+    }
+
+    /// Returns every (absolute) reference into a document other than the root.
+    pub fn external_refs(&self) -> impl Iterator<Item = &str> {
+        self.refs
+            .iter()
+            .filter(move |ref_| self.locate(split_ref(ref_).0).0 != self.root)
+            .map(|ref_| &ref_[..])
+    }
+
+    /// Compiles the root schema into a validator for JSON values, resolving references into
+    /// the loaded documents.
+    pub fn validator(&self) -> Result<Validator, SchemaError> {
+        Validator::with_documents(
+            &self.root,
+            &self.values[&self.root],
+            self.values
+                .iter()
+                .map(|(uri, document)| (&uri[..], document)),
+        )
+    }
+
+    /// Returns the document and the JSON pointer within it of the schema at `uri`.
+    pub(crate) fn locate<'s>(&'s self, uri: &'s str) -> (&'s str, &'s str) {
+        match self.ids.get(uri) {
+            Some((document, pointer)) => (document, pointer),
+            None => (uri, ""),
+        }
+    }
+
+    /// Returns the JSON of the loaded document (or root) at `uri`.
+    pub(crate) fn value(&self, uri: &str) -> Option<&Value> {
+        self.values.get(uri)
+    }
+
+    fn is_loaded(&self, uri: &str) -> bool {
+        uri == self.root || self.documents.contains_key(uri) || self.ids.contains_key(uri)
+    }
+
+    /// Rewrites every `$ref` in `value`, the document located at `uri`, to be absolute and
+    /// returns the documents they point into.
+    fn absolutize(&mut self, uri: &str, value: &mut Value) -> Vec<String> {
+        let mut documents = Vec::new();
+        visit(
+            value,
+            false,
+            uri,
+            "",
+            &mut |base, pointer, node| match node {
+                Node::Id => {
+                    self.ids
+                        .insert(base.to_string(), (uri.to_string(), pointer.to_string()));
+                }
+                Node::Ref(ref_) => {
+                    let (document, fragment) = split_ref(ref_);
+                    let document = resolve_uri(base, document);
+                    *ref_ = format!("{}#{}", document, fragment);
+                    self.refs.insert(ref_.clone());
+                    documents.push(document);
+                }
+            },
+        );
+        documents
+    }
+}
+
+/// Returns every `$ref` in `schema`.
+pub(crate) fn refs(schema: &Schema) -> Vec<String> {
+    let mut refs = Vec::new();
+    let mut value = schema.to_value();
+    visit(&mut value, false, "", "", &mut |_, _, node| {
+        if let Node::Ref(ref_) = node {
+            refs.push(ref_.clone());
+        }
+    });
+    refs
+}
+
+enum Node<'a> {
+    /// A schema identified by an `id`, the base URI is its URI
+    Id,
+    Ref(&'a mut String),
+}
+
+/// Calls `f` with the base URI and JSON pointer of every `$ref` and `id` in the schema `value`.
+/// `names` is set when the keys of `value` are names (such as the keys of `properties`) rather
+/// than keywords.
+fn visit(
+    value: &mut Value,
+    names: bool,
+    base: &str,
+    pointer: &str,
+    f: &mut impl FnMut(&str, &str, Node<'_>),
+) {
+    match value {
+        Value::Object(object) => {
+            let id = match (object.get("$id"), object.get("id")) {
+                (Some(Value::String(id)), _) | (None, Some(Value::String(id))) if !names => {
+                    Some(split_ref(id).0).filter(|id| !id.is_empty())
+                }
+                _ => None,
+            };
+            let base = match id {
+                Some(id) => {
+                    let base = resolve_uri(base, id);
+                    f(&base, pointer, Node::Id);
+                    base
+                }
+                None => base.to_string(),
+            };
+            for (key, value) in object {
+                let pointer = format!("{}/{}", pointer, key.replace('~', "~0").replace('/', "~1"));
+                match (names, &key[..], value) {
+                    (false, "$ref", Value::String(ref_)) => f(&base, &pointer, Node::Ref(ref_)),
+                    // Values which are instances rather than schemas
+                    (false, "const", _)
+                    | (false, "default", _)
+                    | (false, "enum", _)
+                    | (false, "examples", _) => (),
+                    (false, "definitions", value)
+                    | (false, "$defs", value)
+                    | (false, "dependentSchemas", value)
+                    | (false, "patternProperties", value)
+                    | (false, "properties", value) => visit(value, true, &base, &pointer, f),
+                    (_, _, value) => visit(value, false, &base, &pointer, f),
+                }
+            }
+        }
+        Value::Array(array) => {
+            for (i, value) in array.iter_mut().enumerate() {
+                visit(value, false, base, &format!("{}/{}", pointer, i), f);
+            }
+        }
+        _ => (),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn id_scopes() {
+        let root: Schema = r##"{
+                "id": "http://example.com/root.json",
+                "properties": { "a": { "$ref": "#/definitions/a" } },
+                "definitions": {
+                    "a": { "id": "nested/", "items": { "$ref": "b.json" } },
+                    "c": { "id": "http://example.com/c.json" }
+                }
+            }"##
+        .parse()
+        .unwrap();
+        let mut resolver = crate::MemoryResolver::new();
+        resolver.insert("http://example.com/nested/b.json", "{}");
+        let store = SchemaStore::load_with("/schemas/root.json", &root, &resolver).unwrap();
+        assert_eq!(
+            store.documents().collect::<Vec<_>>(),
+            ["http://example.com/nested/b.json"]
+        );
+        assert_eq!(
+            store.root_schema().properties["a"]._ref.as_deref(),
+            Some("http://example.com/root.json#/definitions/a")
+        );
+        assert_eq!(
+            store.locate("http://example.com/root.json"),
+            ("/schemas/root.json", "")
+        );
+        assert_eq!(
+            store.locate("http://example.com/c.json"),
+            ("/schemas/root.json", "/definitions/c")
+        );
+    }
+
+    #[test]
+    fn root_dot_segments() {
+        let root: Schema = r##"{ "properties": { "a": { "$ref": "#/definitions/a" } } }"##
+            .parse()
+            .unwrap();
+        let resolver = crate::MemoryResolver::new();
+        let store =
+            SchemaStore::load_with("/schemas/nested/../root.json", &root, &resolver).unwrap();
+        assert_eq!(store.root(), "/schemas/root.json");
+        assert_eq!(
+            store.root_schema().properties["a"]._ref.as_deref(),
+            Some("/schemas/root.json#/definitions/a")
+        );
+    }
+}
@@ -1,4 +1,4 @@
-use schemafy_lib::Expander;
+use schemafy_lib::{Draft, ExclusiveLimit, Expander, Items, MemoryResolver, Schema, SchemaStore};
 
 #[test]
 fn schema() {
@@ -11,5 +11,255 @@ fn schema() {
         &schema,
     );
     
-    expander.expand(&schema);
-}
\ No newline at end of file
+    expander.expand(&schema).unwrap();
+}
+
+#[test]
+fn draft() {
+    assert_eq!(Draft::from_uri("http://json-schema.org/draft-04/schema#"), Some(Draft::Draft4));
+    assert_eq!(Draft::from_uri("http://json-schema.org/draft-06/schema#"), Some(Draft::Draft6));
+    assert_eq!(Draft::from_uri("https://json-schema.org/draft-07/schema"), Some(Draft::Draft7));
+    assert_eq!(
+        Draft::from_uri("https://json-schema.org/draft/2020-12/schema"),
+        Some(Draft::Draft202012)
+    );
+    assert_eq!(Draft::from_uri("http://example.com/schema"), None);
+
+    let schema: Schema = serde_json::from_str("{}").unwrap();
+    assert_eq!(Draft::of(&schema), Draft::Draft4);
+}
+
+#[test]
+fn boolean_schemas() {
+    let schema: Schema = r#"{
+            "properties": { "any": true, "none": false },
+            "items": false,
+            "exclusiveMinimum": 1
+        }"#
+    .parse()
+    .unwrap();
+    assert_eq!(schema.properties["any"], Schema::default());
+    assert_eq!(schema.properties["none"].not, Some(Box::new(Schema::default())));
+    assert_eq!(
+        schema.items,
+        Some(Box::new(Items::Schema(Schema::from_value(false.into()).unwrap())))
+    );
+    assert_eq!(schema.exclusive_minimum, Some(ExclusiveLimit::Number(1.0)));
+}
+
+#[test]
+fn memory_resolver() {
+    let schema: Schema = serde_json::from_str(
+        r#"{ "properties": { "id": { "$ref": "https://example.com/ids.json#/definitions/id" } } }"#,
+    )
+    .unwrap();
+    let mut resolver = MemoryResolver::new();
+    resolver.insert(
+        "https://example.com/ids.json",
+        r#"{ "definitions": { "id": { "type": "string" } } }"#,
+    );
+    let store = SchemaStore::load_with("https://example.com/root.json", &schema, &resolver).unwrap();
+    assert_eq!(store.documents().collect::<Vec<_>>(), ["https://example.com/ids.json"]);
+
+    let mut expander = Expander::new(Some("Root"), "UNUSED", &schema).with_store(&store);
+    let code = expander.expand(&schema).unwrap().to_string();
+    assert!(code.contains("pub type Id = String"), "{}", code);
+    assert!(code.contains("pub id : Option < Id >"), "{}", code);
+}
+
+#[test]
+fn store_scopes() {
+    let schema: Schema = serde_json::from_str(
+        r##"{
+            "id": "https://example.com/root.json",
+            "properties": { "a": { "$ref": "#/definitions/a" } },
+            "definitions": {
+                "a": { "id": "nested/", "properties": { "b": { "$ref": "b.json" } } }
+            }
+        }"##,
+    )
+    .unwrap();
+    let mut resolver = MemoryResolver::new();
+    resolver.insert("https://example.com/nested/b.json", r#"{ "title": "B", "type": "string" }"#);
+    let store = SchemaStore::load_with("https://example.com/root.json", &schema, &resolver).unwrap();
+
+    // The references of the root are resolved within the scopes of their `id`s
+    let mut expander = Expander::new(Some("Root"), "UNUSED", &schema).with_store(&store);
+    let code = expander.expand(&schema).unwrap().to_string();
+    assert!(code.contains("pub type B = String"), "{}", code);
+    assert!(code.contains("pub b : Option < B >"), "{}", code);
+}
+
+#[test]
+fn errors() {
+    let schema: Schema = serde_json::from_str(
+        r##"{
+            "definitions": {
+                "a/b": {
+                    "properties": { "items": { "type": "array", "items": { "$ref": "#/definitions/missing" } } }
+                }
+            }
+        }"##,
+    )
+    .unwrap();
+    let mut expander = Expander::new(None, "UNUSED", &schema);
+    let err = expander.expand(&schema).unwrap_err();
+    assert_eq!(err.document(), None);
+    assert_eq!(err.pointer(), "/definitions/a~1b/properties/items/items");
+    assert_eq!(err.reason(), "Expected a schema at `#/definitions/missing`");
+}
+
+#[test]
+fn validator() {
+    let schema: Schema = serde_json::from_str(
+        r#"{
+            "type": "object",
+            "properties": {
+                "id": { "$ref": "https://example.com/ids.json#/definitions/id" },
+                "count": { "type": "integer", "minimum": 1 }
+            }
+        }"#,
+    )
+    .unwrap();
+    let mut resolver = MemoryResolver::new();
+    resolver.insert(
+        "https://example.com/ids.json",
+        r#"{ "definitions": { "id": { "type": "string", "pattern": "^[0-9]+$" } } }"#,
+    );
+    let store = SchemaStore::load_with("https://example.com/root.json", &schema, &resolver).unwrap();
+    let validator = store.validator().unwrap();
+    assert!(validator.is_valid(&serde_json::json!({ "id": "12", "count": 1 })));
+
+    let errors = validator
+        .validate(&serde_json::json!({ "id": "a", "count": 0 }))
+        .unwrap_err();
+    let pointers = errors.iter().map(|error| error.pointer()).collect::<Vec<_>>();
+    assert_eq!(pointers, ["/count", "/id"]);
+
+    let err = schema.validator().unwrap_err();
+    assert_eq!(err.uri(), "https://example.com/ids.json#/definitions/id");
+}
+
+#[test]
+fn all_of() {
+    let json = std::fs::read_to_string("src/schema.json").expect("Read schema JSON file");
+    let schema: Schema = serde_json::from_str(&json).unwrap();
+    let mut expander = Expander::new(Some("Schema"), "UNUSED", &schema);
+    let code = expander.expand(&schema).unwrap().to_string();
+    assert!(code.contains("pub type PositiveIntegerDefault0 = i64 ;"), "{}", code);
+    let mut expander =
+        Expander::new(Some("Schema"), "UNUSED", &schema).with_integer_inference(true);
+    let code = expander.expand(&schema).unwrap().to_string();
+    assert!(code.contains("pub type PositiveIntegerDefault0 = u64 ;"), "{}", code);
+
+    let schema: Schema = serde_json::from_str(
+        r##"{
+            "definitions": {
+                "positive": { "type": "integer", "minimum": 1 },
+                "small": {
+                    "allOf": [{ "$ref": "#/definitions/positive" }, { "maximum": 0 }]
+                }
+            }
+        }"##,
+    )
+    .unwrap();
+    let mut expander = Expander::new(None, "UNUSED", &schema);
+    let err = expander.expand(&schema).unwrap_err();
+    assert_eq!(err.pointer(), "/definitions/small");
+    assert_eq!(
+        err.reason(),
+        "`allOf/1` contradicts the other schemas: no number lies within the limits"
+    );
+}
+
+#[test]
+fn colliding_names() {
+    let schema: Schema = serde_json::from_str(
+        r#"{
+            "properties": {
+                "a": { "$ref": "https://example.com/a.json#/definitions/id" },
+                "b": { "$ref": "https://example.com/b.json#/definitions/id" },
+                "c": { "$ref": "https://example.com/b.json#/definitions/id" }
+            }
+        }"#,
+    )
+    .unwrap();
+    let mut resolver = MemoryResolver::new();
+    resolver.insert(
+        "https://example.com/a.json",
+        r#"{ "definitions": { "id": { "type": "string" } } }"#,
+    );
+    resolver.insert(
+        "https://example.com/b.json",
+        r#"{ "definitions": { "id": { "type": "integer" } } }"#,
+    );
+    let store = SchemaStore::load_with("https://example.com/root.json", &schema, &resolver).unwrap();
+    let mut expander = Expander::new(Some("Root"), "UNUSED", &schema).with_store(&store);
+    let err = expander.expand_root().unwrap_err();
+    assert_eq!(err.document(), Some("https://example.com/b.json"));
+    assert_eq!(err.pointer(), "/definitions/id");
+    assert_eq!(
+        err.reason(),
+        "The type `Id` is also generated for the schema at `https://example.com/a.json#/definitions/id`"
+    );
+
+    // A schema referred to several times is generated once
+    let schema: Schema = serde_json::from_str(
+        r#"{
+            "properties": {
+                "b": { "$ref": "https://example.com/b.json#/definitions/id" },
+                "c": { "$ref": "https://example.com/b.json#/definitions/id" }
+            }
+        }"#,
+    )
+    .unwrap();
+    let store = SchemaStore::load_with("https://example.com/root.json", &schema, &resolver).unwrap();
+    let mut expander = Expander::new(Some("Root"), "UNUSED", &schema).with_store(&store);
+    let code = expander.expand_root().unwrap().to_string();
+    assert_eq!(code.matches("pub type Id").count(), 1, "{}", code);
+}
+
+#[test]
+fn identifiers() {
+    let schema: Schema = serde_json::from_str(
+        r#"{
+            "definitions": {
+                "1st": { "type": "object", "properties": { "a": { "type": "string" } } },
+                "Self": { "enum": ["", "1", "a b", "Self"] },
+                "choice": { "oneOf": [{ "title": "2", "type": "string" }, { "type": "integer" }] }
+            }
+        }"#,
+    )
+    .unwrap();
+    let mut expander = Expander::new(None, "UNUSED", &schema);
+    let code = expander.expand(&schema).unwrap().to_string();
+    assert!(code.contains("pub struct _1St"), "{}", code);
+    assert!(code.contains("pub enum Self_"), "{}", code);
+    assert!(code.contains("# [serde (rename = \"\")] Empty"), "{}", code);
+    assert!(code.contains("# [serde (rename = \"1\")] _1"), "{}", code);
+    assert!(code.contains("_2 (String)"), "{}", code);
+
+    let schema: Schema = serde_json::from_str(r#"{ "enum": [true], "enumNames": ["yes"] }"#).unwrap();
+    let mut expander = Expander::new(Some("Flag"), "UNUSED", &schema);
+    let err = expander.expand(&schema).unwrap_err();
+    assert_eq!(err.reason(), "Expected string or number for enum got `true`");
+}
+
+#[test]
+fn unsupported_patterns() {
+    let schema: Schema = serde_json::from_str(
+        r#"{
+            "type": "object",
+            "properties": { "a": { "type": "string", "pattern": "(?!a)" } }
+        }"#,
+    )
+    .unwrap();
+    let mut expander = Expander::new(Some("Root"), "UNUSED", &schema).with_validation(true);
+    let err = expander.expand_root().unwrap_err();
+    assert_eq!(err.pointer(), "/properties/a");
+    assert!(err.reason().starts_with(r#"Unsupported pattern "(?!a)""#));
+
+    // Patterns are only compiled when they are checked
+    let mut expander = Expander::new(Some("Root"), "UNUSED", &schema);
+    assert!(expander.expand_root().is_ok());
+}
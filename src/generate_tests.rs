@@ -2,7 +2,13 @@
 
 use inflector::Inflector;
 use serde::{Deserialize, Serialize};
-use std::{error::Error, ffi::OsStr, fs, path::PathBuf};
+use std::{
+    collections::BTreeMap,
+    error::Error,
+    ffi::OsStr,
+    fs,
+    path::{Path, PathBuf},
+};
 
 // Each test has a description, schema, and a list of tests. Each of
 // those tests has a description, some data, and a `valid` field which
@@ -12,6 +18,13 @@ schemafy::schemafy!(
     "tests/JSON-Schema-Test-Suite/test-schema.json"
 );
 
+/// The drafts of the test suite which are generated, each into a module of
+/// its own, along with the `$schema` URI identifying them.
+///
+/// A draft is only generated once it has a blacklist of its own (see
+/// `is_blacklisted`), as the groups at an index differ between the drafts.
+const DRAFTS: &[(&str, &str)] = &[("draft4", "http://json-schema.org/draft-04/schema#")];
+
 fn main() -> Result<(), Box<dyn Error>> {
     let test_suite_dir = PathBuf::from("tests/test_suite");
     let schemas_dir = test_suite_dir.join("schemas");
@@ -21,18 +34,27 @@ fn main() -> Result<(), Box<dyn Error>> {
     fs::create_dir(&test_suite_dir)?;
     fs::create_dir(&schemas_dir)?;
 
-    let mut test_modules = vec![];
+    let mut test_modules = BTreeMap::<String, Vec<String>>::new();
     let mut blacklist_count = 0;
 
-    for path in fs::read_dir("tests/JSON-Schema-Test-Suite/tests/draft4")?
-        .map(|entry| entry.unwrap().path())
-        .filter(|path| path.extension() == Some(OsStr::new("json")))
+    let mut paths = vec![];
+    for (draft, _) in DRAFTS {
+        fs::create_dir(test_suite_dir.join(module_name(draft)))?;
+        fs::create_dir(schemas_dir.join(draft))?;
+        for entry in fs::read_dir(format!("tests/JSON-Schema-Test-Suite/tests/{}", draft))? {
+            paths.push((*draft, entry?.path()));
+        }
+    }
+
+    for (draft, path) in paths
+        .into_iter()
+        .filter(|(_, path)| path.extension() == Some(OsStr::new("json")))
     {
         let buffer = fs::read_to_string(&path)?;
         let test_schema: TestSchema = serde_json::from_str(&buffer)?;
         println!("{} ==> {} tests", path.display(), test_schema.len());
 
-        let module_name = path.file_stem().unwrap().to_str().unwrap().to_snake_case();
+        let module_name = module_name(path.file_stem().unwrap().to_str().unwrap());
 
         let mut test_file: String = format!(
             r#"//! Automatically generated from {}
@@ -41,14 +63,21 @@ fn main() -> Result<(), Box<dyn Error>> {
         );
 
         for (i, test_group) in test_schema.iter().enumerate() {
-            if is_blacklisted(&module_name, i) {
+            if is_blacklisted(draft, &module_name, i) || refers_to_meta_schema(&test_group.schema) {
                 blacklist_count += 1;
                 println!(" !! skipping test group: {}", test_group.description);
                 continue;
             }
 
-            let schema_name = format!("{}_{}.json", module_name, i);
-            let schema = serde_json::to_string(&test_group.schema)?;
+            let schema_name = format!("{}/{}_{}.json", draft, module_name, i);
+            // The test schemas do not declare which draft they are written
+            // against.
+            let mut schema = test_group.schema.clone();
+            if let Some(schema) = schema.as_object_mut() {
+                let uri = DRAFTS.iter().find(|(name, _)| *name == draft).unwrap().1;
+                schema.entry("$schema").or_insert_with(|| uri.into());
+            }
+            let schema = serde_json::to_string(&schema)?;
             fs::write(schemas_dir.join(&schema_name), schema)?;
 
             test_file.push_str(&format!(
@@ -57,7 +86,12 @@ mod _{}_{} {{
     #[allow(unused_imports)]
     use serde::{{Deserialize, Serialize}};
 
-    schemafy::schemafy!(root: Schema "tests/test_suite/schemas/{}");
+    schemafy::schemafy!(
+        root: Schema
+        resolve: "http://localhost:1234/" => "tests/JSON-Schema-Test-Suite/remotes"
+        validate: deserialize
+        "tests/test_suite/schemas/{}"
+    );
 "#,
                 i,
                 test_group.description.to_snake_case(),
@@ -97,20 +131,23 @@ mod _{}_{} {{
         }
 
         fs::write(
-            test_suite_dir.join(format!("{}.rs", module_name)),
+            test_suite_dir
+                .join(self::module_name(draft))
+                .join(format!("{}.rs", module_name)),
             test_file,
         )?;
-        test_modules.push(module_name);
+        test_modules
+            .entry(self::module_name(draft))
+            .or_default()
+            .push(module_name);
     }
 
-    // Generate a root module that declares all the above files.
-    let mut tests: String = r#"//! Automatically generated
-"#
-    .into();
-    for module in &test_modules {
-        tests.push_str(&format!("mod r#{};\n", module));
+    // Generate a module for each draft that declares all the above files
+    // and a root module that declares the drafts.
+    for (draft, modules) in &test_modules {
+        write_mod(&test_suite_dir.join(draft), modules)?;
     }
-    fs::write(test_suite_dir.join("mod.rs"), tests)?;
+    write_mod(&test_suite_dir, &test_modules.keys().collect::<Vec<_>>())?;
 
     if blacklist_count > 0 {
         println!("\nSkipped {} test schemas\n", blacklist_count);
@@ -119,10 +156,42 @@ mod _{}_{} {{
     Ok(())
 }
 
+fn module_name(name: &str) -> String {
+    name.to_snake_case()
+}
+
+fn write_mod(dir: &Path, modules: &[impl AsRef<str>]) -> Result<(), Box<dyn Error>> {
+    let mut tests: String = r#"//! Automatically generated
+"#
+    .into();
+    for module in modules {
+        tests.push_str(&format!("mod r#{};\n", module.as_ref()));
+    }
+    fs::write(dir.join("mod.rs"), tests)?;
+    Ok(())
+}
+
+/// Test groups which refer to the meta-schemas of the drafts, which are not
+/// part of the suite.
+fn refers_to_meta_schema(schema: &serde_json::Value) -> bool {
+    schema.to_string().contains("json-schema.org/draft")
+}
+
 /// To allow for gradual progress, this function determines whether a
 /// test should be skipped.
-fn is_blacklisted(test_group: &str, index: usize) -> bool {
-    let blacklisted_indices: &[usize] = match test_group {
+///
+/// Each draft has a blacklist of its own, built by running the generated
+/// tests of that draft.
+fn is_blacklisted(draft: &str, test_group: &str, index: usize) -> bool {
+    let blacklisted_indices = match draft {
+        "draft4" => draft4_blacklist(test_group),
+        _ => panic!("No blacklist for {}", draft),
+    };
+    blacklisted_indices.contains(&index)
+}
+
+fn draft4_blacklist(test_group: &str) -> &'static [usize] {
+    match test_group {
         "additional_items" => &[0, 2],
         "additional_properties" => &[0, 1, 2, 3, 5],
         "all_of" => &[1, 2, 5, 6],
@@ -152,7 +221,5 @@ fn is_blacklisted(test_group: &str, index: usize) -> bool {
         "unique_items" => &[0, 1, 2],
 
         _ => &[],
-    };
-
-    blacklisted_indices.contains(&index)
+    }
 }
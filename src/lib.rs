@@ -4,10 +4,13 @@
 // #![doc(include = "../README.md")]
 
 //! This is a Rust crate which can take a [json schema (draft
-//! 4)](http://json-schema.org/) and generate Rust types which are
-//! serializable with [serde](https://serde.rs/). No checking such as
-//! `min_value` are done but instead only the structure of the schema
-//! is followed as closely as possible.
+//! 4, 6, 7, 2019-09 or 2020-12)](http://json-schema.org/) and generate Rust types which are
+//! serializable with [serde](https://serde.rs/). By default the types
+//! only follow the structure of the schema as closely as possible.
+//! Checking constraints such as `minimum` is opt-in, through a generated
+//! `validate` method (`validate: true`), when deserializing
+//! (`validate: deserialize`) or through newtypes for constrained
+//! definitions (`newtypes: true`).
 //!
 //! As a schema could be arbitrarily complex this crate makes no
 //! guarantee that it can generate good types or even any types at all
@@ -49,9 +52,12 @@
 //! }
 //! ```
 
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
-use schemafy_lib::Expander;
+use schemafy_lib::{
+    DirectoryResolver, Expander, FileResolver, Formats, NumberType, Schema, SchemaResolver,
+    SchemaStore,
+};
 
 /// A configurable builder for generating Rust types from a JSON
 /// schema.
@@ -68,6 +74,9 @@ struct GenerateBuilder<'a> {
     /// re-exported this crate or imported it under a different name,
     /// the default should be fine.
     pub schemafy_path: &'a str,
+    /// Whether the generated code should make cargo rebuild the
+    /// crate when one of the schema files changes.
+    pub track_dependencies: bool,
 }
 
 impl<'a> Default for GenerateBuilder<'a> {
@@ -75,38 +84,176 @@ impl<'a> Default for GenerateBuilder<'a> {
         GenerateBuilder {
             root_name: None,
             schemafy_path: "::schemafy_core::",
+            track_dependencies: true,
         }
     }
 }
 
-impl<'a> GenerateBuilder<'a> {
-    fn build_tokens(mut self, tokens: proc_macro::TokenStream) -> proc_macro::TokenStream {
-        struct Def {
-            root: Option<String>,
-            input_file: syn::LitStr,
-        }
+struct Def {
+    root: Option<String>,
+    resolve: Vec<(String, String)>,
+    validate: bool,
+    validate_on_deserialize: bool,
+    newtypes: bool,
+    formats: Formats,
+    infer_integers: bool,
+    number_type: NumberType,
+    decimal_type: Option<NumberType>,
+    inherit: bool,
+    input_file: syn::LitStr,
+}
 
-        impl syn::parse::Parse for Def {
-            fn parse(input: syn::parse::ParseStream<'_>) -> syn::Result<Self> {
-                let root = if input.peek(syn::Ident) {
-                    let root_ident: syn::Ident = input.parse()?;
-                    if root_ident != "root" {
-                        return Err(syn::Error::new(root_ident.span(), "Expected `root`"));
-                    }
-                    input.parse::<syn::Token![:]>()?;
-                    Some(input.parse::<syn::Ident>()?.to_string())
+impl syn::parse::Parse for Def {
+    fn parse(input: syn::parse::ParseStream<'_>) -> syn::Result<Self> {
+        let mut root = None;
+        let mut resolve = Vec::new();
+        let mut validate = false;
+        let mut validate_on_deserialize = false;
+        let mut newtypes = false;
+        let mut formats = Formats::default();
+        let mut infer_integers = false;
+        let mut number_type = NumberType::F64;
+        let mut decimal_type = None;
+        let mut inherit = false;
+        while input.peek(syn::Ident) {
+            let ident: syn::Ident = input.parse()?;
+            input.parse::<syn::Token![:]>()?;
+            if ident == "root" {
+                root = Some(input.parse::<syn::Ident>()?.to_string());
+            } else if ident == "resolve" {
+                let prefix = input.parse::<syn::LitStr>()?.value();
+                input.parse::<syn::Token![=>]>()?;
+                resolve.push((prefix, input.parse::<syn::LitStr>()?.value()));
+            } else if ident == "validate" {
+                if input.peek(syn::LitBool) {
+                    validate = input.parse::<syn::LitBool>()?.value;
                 } else {
-                    None
-                };
-                Ok(Def {
-                    root,
-                    input_file: input.parse()?,
-                })
+                    let mode = input.parse::<syn::Ident>()?;
+                    if mode != "deserialize" {
+                        return Err(syn::Error::new(
+                            mode.span(),
+                            "Expected `true`, `false` or `deserialize`",
+                        ));
+                    }
+                    validate_on_deserialize = true;
+                }
+            } else if ident == "newtypes" {
+                newtypes = input.parse::<syn::LitBool>()?.value;
+            } else if ident == "formats" {
+                let content;
+                syn::bracketed!(content in input);
+                let crates =
+                    syn::punctuated::Punctuated::<syn::Ident, syn::Token![,]>::parse_terminated(
+                        &content,
+                    )?;
+                for krate in crates {
+                    let enabled = if krate == "chrono" {
+                        formats.chrono = true;
+                        cfg!(feature = "chrono")
+                    } else if krate == "time" {
+                        formats.time = true;
+                        cfg!(feature = "time")
+                    } else if krate == "uuid" {
+                        formats.uuid = true;
+                        cfg!(feature = "uuid")
+                    } else if krate == "url" {
+                        formats.url = true;
+                        cfg!(feature = "url")
+                    } else if krate == "ip" {
+                        formats.ip = true;
+                        true
+                    } else {
+                        return Err(syn::Error::new(
+                            krate.span(),
+                            "Expected `chrono`, `time`, `uuid`, `url` or `ip`",
+                        ));
+                    };
+                    if !enabled {
+                        return Err(syn::Error::new(
+                            krate.span(),
+                            format!(
+                                "`formats: [{}]` requires the `{}` feature of schemafy",
+                                krate, krate
+                            ),
+                        ));
+                    }
+                }
+            } else if ident == "infer_integers" {
+                infer_integers = input.parse::<syn::LitBool>()?.value;
+            } else if ident == "numbers" {
+                number_type = parse_number_type(&ident, input)?;
+            } else if ident == "decimals" {
+                decimal_type = Some(parse_number_type(&ident, input)?);
+            } else if ident == "inherit" {
+                inherit = input.parse::<syn::LitBool>()?.value;
+            } else {
+                return Err(syn::Error::new(
+                    ident.span(),
+                    "Expected `root`, `resolve`, `validate`, `newtypes`, `formats`, \
+                     `infer_integers`, `numbers`, `decimals` or `inherit`",
+                ));
             }
+            if input.peek(syn::Token![,]) {
+                input.parse::<syn::Token![,]>()?;
+            }
+        }
+        Ok(Def {
+            root,
+            resolve,
+            validate,
+            validate_on_deserialize,
+            newtypes,
+            formats,
+            infer_integers,
+            number_type,
+            decimal_type,
+            inherit,
+            input_file: input.parse()?,
+        })
+    }
+}
+
+/// Parses the type of the `numbers` or `decimals` parameter `ident`.
+fn parse_number_type(
+    ident: &syn::Ident,
+    input: syn::parse::ParseStream<'_>,
+) -> syn::Result<NumberType> {
+    let typ = input.parse::<syn::Ident>()?;
+    if typ == "f64" {
+        Ok(NumberType::F64)
+    } else if typ == "decimal" {
+        if !cfg!(feature = "decimal") {
+            return Err(syn::Error::new(
+                typ.span(),
+                format!(
+                    "`{}: decimal` requires the `decimal` feature of schemafy",
+                    ident
+                ),
+            ));
         }
+        Ok(NumberType::Decimal)
+    } else if typ == "number" {
+        Ok(NumberType::Number)
+    } else {
+        Err(syn::Error::new(
+            typ.span(),
+            "Expected `f64`, `decimal` or `number`",
+        ))
+    }
+}
 
+impl<'a> GenerateBuilder<'a> {
+    fn build_tokens(mut self, tokens: proc_macro::TokenStream) -> proc_macro::TokenStream {
         let def = syn::parse_macro_input!(tokens as Def);
-        self.root_name = def.root;
+        self.root_name = def.root.clone();
+        self.expand(&def)
+            .map(proc_macro::TokenStream::from)
+            .unwrap_or_else(|err| err.to_compile_error().into())
+    }
+
+    /// Generates the types for `def`. Errors are reported on the path to the schema.
+    fn expand(&self, def: &Def) -> syn::Result<proc_macro2::TokenStream> {
+        let error = |message: String| syn::Error::new(def.input_file.span(), message);
 
         let input_file = PathBuf::from(def.input_file.value());
         let crate_root = PathBuf::from(std::env::var("CARGO_MANIFEST_DIR").unwrap());
@@ -117,24 +264,139 @@ impl<'a> GenerateBuilder<'a> {
             input_file
         };
 
-        let json = std::fs::read_to_string(&input_path)
-            .unwrap_or_else(|err| panic!("Unable to read `{}`: {}", input_path.to_string_lossy(), err));
+        let directories = def
+            .resolve
+            .iter()
+            .map(|(prefix, directory)| DirectoryResolver::new(prefix, crate_root.join(directory)))
+            .collect::<Vec<_>>();
+        let mut resolvers = directories
+            .iter()
+            .map(|resolver| Box::new(resolver) as Box<dyn SchemaResolver>)
+            .collect::<Vec<_>>();
+        resolvers.push(Box::new(FileResolver));
+
+        let json = std::fs::read_to_string(&input_path).map_err(|err| {
+            error(format!("Unable to read `{}`: {}", input_path.to_string_lossy(), err))
+        })?;
 
-        let schema = serde_json::from_str(&json).unwrap_or_else(|err| panic!("{}", err));
+        let schema = json.parse::<Schema>().map_err(|err| {
+            // Report the position as `path:line:column` which editors can jump to, the
+            // diagnostic itself can only point at the macro invocation
+            if err.line() == 0 {
+                return error(format!("{}: {}", input_path.to_string_lossy(), err));
+            }
+            let position = format!(" at line {} column {}", err.line(), err.column());
+            error(format!(
+                "{}:{}:{}: {}",
+                input_path.to_string_lossy(),
+                err.line(),
+                err.column(),
+                err.to_string().trim_end_matches(&position[..])
+            ))
+        })?;
+        let store = SchemaStore::load_with(&input_path, &schema, &resolvers)
+            .map_err(|err| error(err.to_string()))?;
         let mut expander = Expander::new(
             self.root_name.as_ref().map(|s| &**s),
             self.schemafy_path,
             &schema,
-        );
-        expander.expand(&schema).into()
+        )
+        .with_store(&store)
+        .with_validation(def.validate)
+        .with_validation_on_deserialize(def.validate_on_deserialize)
+        .with_newtypes(def.newtypes)
+        .with_integer_inference(def.infer_integers)
+        .with_number_type(def.number_type)
+        .with_inheritance(def.inherit)
+        .with_decimal_type(def.decimal_type.unwrap_or(def.number_type))
+        .with_formats(def.formats);
+        let mut tokens = expander
+            .expand_root()
+            .map_err(|err| error(err.to_string()))?;
+
+        if self.track_dependencies {
+            // Make cargo rebuild the crate whenever one of the schema files changes
+            let paths = std::iter::once(input_path.clone()).chain(
+                store
+                    .documents()
+                    .filter_map(|uri| document_path(&directories, uri)),
+            );
+            for path in paths {
+                let dependency = format!(
+                    "const _: &[u8] = include_bytes!({:?});",
+                    path.to_string_lossy()
+                );
+                tokens.extend(dependency.parse::<proc_macro2::TokenStream>().unwrap());
+            }
+        }
+        Ok(tokens)
     }
 }
 
+/// Returns the file which the document at `uri` was read from, if it was read from a file.
+fn document_path(directories: &[DirectoryResolver], uri: &str) -> Option<PathBuf> {
+    directories
+        .iter()
+        .filter_map(|resolver| resolver.path(uri))
+        .find(|path| path.is_file())
+        .or_else(|| {
+            let path = Path::new(uri.strip_prefix("file://").unwrap_or(uri));
+            Some(path.to_path_buf()).filter(|path| path.is_absolute() && path.is_file())
+        })
+}
+
 /// Generate Rust types from a JSON schema.
 ///
 /// If the `root` parameter is supplied, then a type will be
 /// generated from the root of the schema.
 ///
+/// `$ref`s to other documents are read from the filesystem, relative to
+/// the referring document. Each `resolve` parameter serves the documents
+/// whose URIs start with a prefix from a directory (relative to the crate
+/// root) instead, which allows remote schemas to be vendored.
+///
+/// `validate: true` implements `schemafy_core::validate::Validate` for the
+/// generated types, checking constraints such as `minimum`, `maxLength` and
+/// `pattern` which the types themselves do not enforce. With `validate: deserialize`
+/// the constraints are also checked when deserializing, so values violating them
+/// fail to deserialize.
+///
+/// `newtypes: true` turns definitions with constraints, such as
+/// `{"type": "string", "pattern": "^[a-z]+$"}`, into newtypes which can only
+/// be created through a checked `new` constructor, `TryFrom` or deserialization
+/// instead of aliases of `String`, `i64` and so on.
+///
+/// Strings with a `format` are generated as `String` unless the crates listed
+/// by `formats`, such as `formats: [chrono, uuid]`, are used for them:
+/// `chrono` or `time` for `date-time` and `date` (`time` only for properties),
+/// `uuid` for `uuid` as `uuid::Uuid`, `url` for `uri` as `url::Url` and `ip`
+/// for `ipv4` and `ipv6` as `std::net::Ipv4Addr` and `std::net::Ipv6Addr`.
+/// Each crate but `ip` requires the feature of the same name of this crate.
+///
+/// `infer_integers: true` generates integers as the smallest type of `u8`..`u64`
+/// and `i8`..`i64` fitting their `minimum` and `maximum` (or a `format` such as
+/// `int32` or `uint64`) instead of as `i64`.
+///
+/// `numbers: decimal` generates numbers as `rust_decimal::Decimal` (which
+/// requires the `decimal` feature) and `numbers: number` as
+/// `schemafy_core::number::Number` instead of as `f64`. `decimals:` takes the
+/// same types but only applies to the numbers with `format: "decimal"`, such
+/// as `decimals: decimal` for monetary values. Decimal properties are
+/// (de)serialized with `schemafy_core::decimal`, which also reads strings and
+/// only goes through `f64` for numbers whose digits `serde_json` does not keep.
+/// The `arbitrary_precision` feature keeps every digit of both types. It
+/// enables the feature of the same name of `serde_json` for the whole build,
+/// which keeps untagged enums (such as the ones generated for `oneOf`) from
+/// deserializing numbers into `f64`.
+///
+/// `inherit: true` generates a struct whose `allOf` refers to another struct
+/// with a `#[serde(flatten)] pub base` field of the referenced type besides
+/// its own fields, and `Deref` to it, so that the base stays a single type
+/// instead of having its fields copied into every struct extending it.
+///
+/// The crate is rebuilt whenever one of the schema files which were read
+/// changes.
+///
 /// ```rust
 /// extern crate serde;
 /// extern crate schemafy_core;
@@ -166,14 +428,16 @@ pub fn schemafy(tokens: proc_macro::TokenStream) -> proc_macro::TokenStream {
 pub fn regenerate(tokens: proc_macro::TokenStream) -> proc_macro::TokenStream {
     use std::process::Command;
 
+    // The generated code is written to a file so it must not refer to the schema's location
     let tokens = GenerateBuilder {
+        track_dependencies: false,
         ..GenerateBuilder::default()
     }
     .build_tokens(tokens);
 
     {
         let out = tokens.to_string();
-        std::fs::write("src/schema.rs", &out).unwrap();
+        std::fs::write("schemafy_lib/src/schema.rs", &out).unwrap();
         Command::new("rustfmt")
             .arg("schemafy_lib/src/schema.rs")
             .output()
@@ -182,3 +446,72 @@ pub fn regenerate(tokens: proc_macro::TokenStream) -> proc_macro::TokenStream {
 
     tokens
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn expand(def: &str) -> String {
+        let def = syn::parse_str::<Def>(def).unwrap();
+        GenerateBuilder {
+            root_name: def.root.clone(),
+            ..GenerateBuilder::default()
+        }
+        .expand(&def)
+        .unwrap()
+        .to_string()
+    }
+
+    #[test]
+    fn include_bytes() {
+        let code = expand(
+            r#"
+            root: Order
+            resolve: "https://schemas.example.com/" => "tests/vendor"
+            "tests/vendored.json"
+            "#,
+        );
+        let crate_root = Path::new(env!("CARGO_MANIFEST_DIR"));
+        for path in &[
+            "tests/vendored.json",
+            "tests/vendor/money.json",
+            "tests/vendor/currency.json",
+        ] {
+            let dependency = format!(
+                "const _ : & [u8] = include_bytes ! ({:?}) ;",
+                crate_root.join(path).to_string_lossy()
+            );
+            assert!(code.contains(&dependency), "{} in {}", dependency, code);
+        }
+        assert_eq!(code.matches("include_bytes !").count(), 3, "{}", code);
+    }
+
+    #[test]
+    fn formats() {
+        let def = syn::parse_str::<Def>(r#"formats: [ip, ip,] "tests/formats.json""#).unwrap();
+        assert_eq!(
+            def.formats,
+            Formats {
+                ip: true,
+                ..Formats::default()
+            }
+        );
+
+        let err = syn::parse_str::<Def>(r#"formats: [ipv4] "tests/formats.json""#)
+            .err()
+            .unwrap();
+        assert_eq!(
+            err.to_string(),
+            "Expected `chrono`, `time`, `uuid`, `url` or `ip`"
+        );
+        if !cfg!(feature = "uuid") {
+            let err = syn::parse_str::<Def>(r#"formats: [uuid] "tests/formats.json""#)
+                .err()
+                .unwrap();
+            assert_eq!(
+                err.to_string(),
+                "`formats: [uuid]` requires the `uuid` feature of schemafy"
+            );
+        }
+    }
+}
@@ -112,4 +112,718 @@ schemafy::schemafy!(
     "tests/recursive_types.json"
 );
 
-fn recursive_types_exist(_: RecursiveTypes) {}
\ No newline at end of file
+fn recursive_types_exist(_: RecursiveTypes) {}
+
+schemafy::schemafy!(
+    root: OneOf
+    "tests/one-of.json"
+);
+
+#[test]
+fn one_of() {
+    let o: OneOf = serde_json::from_str(r#"{ "value": 1 }"#).unwrap();
+    assert_eq!(o.value, OneOfValue::Integer(1));
+    assert_eq!(o.nullable, None);
+
+    let o: OneOf = serde_json::from_str(r#"{ "value": "a", "nullable": { "x": 1, "y": 2 } }"#)
+        .unwrap();
+    assert_eq!(o.value, OneOfValue::String("a".into()));
+    assert_eq!(o.nullable, Some(Point { x: 1.0, y: 2.0 }));
+
+    let o: OneOf = serde_json::from_str(r#"{ "value": { "x": 1, "y": 2 } }"#).unwrap();
+    assert_eq!(o.value, OneOfValue::Point(Point { x: 1.0, y: 2.0 }));
+
+    let shape: Shape = serde_json::from_str(r#"[{ "x": 1, "y": 2 }]"#).unwrap();
+    assert_eq!(shape, Shape::Polygon(vec![Point { x: 1.0, y: 2.0 }]));
+    assert_eq!(serde_json::to_string(&shape).unwrap(), r#"[{"x":1.0,"y":2.0}]"#);
+}
+
+#[test]
+fn vega_unions() {
+    let mark: AnyMark = serde_json::from_str(r#""bar""#).unwrap();
+    assert_eq!(mark, AnyMark::Mark(Mark::Bar));
+}
+
+schemafy::schemafy!(
+    root: TaggedOneOf
+    "tests/tagged-one-of.json"
+);
+
+#[test]
+fn tagged_one_of() {
+    let message: TaggedOneOf = serde_json::from_str(r#"{ "kind": "ping", "seq": 1 }"#).unwrap();
+    assert_eq!(message, TaggedOneOf::Ping(TaggedOneOfPing { seq: 1 }));
+
+    let message: TaggedOneOf =
+        serde_json::from_str(r#"{ "kind": "move", "seq": 2, "x": 3, "y": 4 }"#).unwrap();
+    assert_eq!(
+        message,
+        TaggedOneOf::Move(TaggedOneOfMove { seq: 2, x: 3, y: 4 })
+    );
+    assert_eq!(
+        serde_json::to_string(&message).unwrap(),
+        r#"{"kind":"move","seq":2,"x":3,"y":4}"#
+    );
+
+    let message: TaggedOneOf = serde_json::from_str(r#"{ "kind": "quit" }"#).unwrap();
+    assert_eq!(message, TaggedOneOf::Quit);
+
+    let err = serde_json::from_str::<TaggedOneOf>(r#"{ "kind": "jump" }"#).unwrap_err();
+    assert!(err.to_string().contains("jump"), "{}", err);
+}
+
+schemafy::schemafy!(
+    root: Draft7
+    "tests/draft7.json"
+);
+
+#[test]
+fn draft7() {
+    let d: Draft7 = serde_json::from_str(
+        r#"{ "kind": "draft7", "version": 2, "anything": [1], "count": 1, "tags": ["rust"] }"#,
+    )
+    .unwrap();
+    assert_eq!(d.kind, Kind::Draft7);
+    assert_eq!(d.version, Some(2));
+    assert_eq!(d.anything, Some(serde_json::json!([1])));
+    assert_eq!(d.count, Some(1));
+}
+
+schemafy::schemafy!(
+    root: Draft202012
+    "tests/draft2020-12.json"
+);
+
+#[test]
+fn draft2020_12() {
+    let d: Draft202012 = serde_json::from_str(
+        r#"{
+            "id": "a",
+            "size": 2,
+            "pair": ["b", 3],
+            "point": [1.5, 2.5],
+            "credit_card": "1234",
+            "billing_address": "street",
+            "labels": { "c": "d" }
+        }"#,
+    )
+    .unwrap();
+    let _: Identifier = d.id;
+    let _: Option<PositiveSize> = d.size;
+    assert_eq!(d.point, Some((1.5, 2.5)));
+    assert_eq!(d.billing_address, Some("street".to_string()));
+    assert_eq!(d.labels.unwrap()["c"], "d");
+    let Closed {} = Closed {};
+}
+
+schemafy::schemafy!(
+    root: Book
+    "tests/external-ref.json"
+);
+
+#[test]
+fn external_ref() {
+    let book: Book = serde_json::from_str(
+        r#"{
+            "author": { "id": 1, "name": "Ann", "address": { "city": "Lund" } },
+            "id": "b-1",
+            "tags": [{ "name": "fiction", "owner": 1 }]
+        }"#,
+    )
+    .unwrap();
+    let _: BookId = book.id;
+    let author: Person = book.author;
+    let _: PersonId = author.id;
+    assert_eq!(author.address.unwrap().city, Some("Lund".to_string()));
+    let tags: Vec<Tag> = book.tags.unwrap();
+    assert_eq!(tags[0].owner, Some(1));
+}
+
+schemafy::schemafy!(
+    root: Order
+    resolve: "https://schemas.example.com/" => "tests/vendor"
+    "tests/vendored.json"
+);
+
+#[test]
+fn vendored_ref() {
+    let order: Order =
+        serde_json::from_str(r#"{ "price": { "amount": 10, "currency": "SEK" } }"#).unwrap();
+    let price: Money = order.price;
+    assert_eq!(price.amount, 10);
+    assert_eq!(price.currency, CurrencyCode::Sek);
+}
+
+schemafy::schemafy!(
+    root: JsonPointer
+    "tests/json-pointer.json"
+);
+
+#[test]
+fn json_pointer() {
+    let p: JsonPointer = serde_json::from_str(
+        r#"{
+            "target": { "x": 1, "y": 2 },
+            "path": [{ "x": 3 }],
+            "label": "a",
+            "slashed": 1,
+            "encoded": true,
+            "first": "b",
+            "alternative": { "value": 4 }
+        }"#,
+    )
+    .unwrap();
+    let target: PropertiesOrigin = p.target.unwrap();
+    assert_eq!(target.y, Some(2.0));
+    assert_eq!(p.path.unwrap()[0].x, Some(3.0));
+    assert_eq!(p.label, Some("a".to_string()));
+    assert_eq!(p.slashed, Some(1));
+    assert_eq!(p.encoded, Some(true));
+    assert_eq!(p.first, Some("b".to_string()));
+    let alternative: ChoiceOneOf1 = p.alternative.unwrap();
+    assert_eq!(alternative.value, Some(4));
+}
+
+schemafy::schemafy!(
+    root: Cart
+    resolve: "https://schemas.example.com/" => "tests/vendor"
+    "tests/id-scope.json"
+);
+
+#[test]
+fn id_scope() {
+    let cart: Cart = serde_json::from_str(
+        r#"{
+            "prices": [1.5],
+            "currency": "EUR",
+            "item": { "sku": "a-1" }
+        }"#,
+    )
+    .unwrap();
+    let prices: Prices = cart.prices.unwrap();
+    let _: Price = prices[0];
+    let _: Option<Currency> = cart.currency;
+    let item: Item = cart.item.unwrap();
+    assert_eq!(item.sku, Some("a-1".to_string()));
+}
+
+schemafy::schemafy!(
+    root: Validated
+    validate: true
+    "tests/validate.json"
+);
+
+// `serde_json` with `arbitrary_precision` can not deserialize the number of `shape` into the `f64`
+// of an untagged enum
+#[cfg(not(feature = "arbitrary_precision"))]
+#[test]
+fn validate() {
+    use schemafy_core::validate::Validate;
+
+    let valid: Validated = serde_json::from_str(
+        r#"{ "name": "ann", "age": 30, "tags": ["a", "b"], "shape": 1.5 }"#,
+    )
+    .unwrap();
+    assert_eq!(valid.validate(), Ok(()));
+
+    let invalid: Validated = serde_json::from_str(
+        r#"{
+            "name": "anastasia",
+            "age": 150,
+            "tags": ["a", "B", "a"],
+            "address": { "zip": "123" },
+            "shape": "long"
+        }"#,
+    )
+    .unwrap();
+    let errors = invalid.validate().unwrap_err();
+    let pointers = errors.iter().map(|error| error.pointer()).collect::<Vec<_>>();
+    assert_eq!(
+        pointers,
+        ["/address/zip", "/age", "/name", "/shape", "/tags", "/tags/1"],
+        "{}",
+        errors
+    );
+}
+
+mod checked {
+    use serde::{Deserialize, Serialize};
+
+    schemafy::schemafy!(
+        root: Validated
+        validate: deserialize
+        "tests/validate.json"
+    );
+}
+
+#[test]
+fn validate_on_deserialize() {
+    let valid: checked::Validated =
+        serde_json::from_str(r#"{ "name": "ann", "tags": ["a"], "shape": "abc" }"#).unwrap();
+    assert_eq!(&*valid.name, "ann");
+    assert_eq!(
+        serde_json::to_value(&valid).unwrap()["name"],
+        serde_json::json!("ann")
+    );
+
+    let err = serde_json::from_str::<checked::Validated>(
+        r#"{ "name": "ann", "tags": ["a"], "address": { "zip": "123" } }"#,
+    )
+    .unwrap_err();
+    // Nested values are checked as they are deserialized, serde reports where they are
+    assert_eq!(
+        err.to_string(),
+        r#"/zip: "123" is shorter than 5 characters at line 1 column 61"#
+    );
+    assert!(serde_json::from_str::<checked::Name>(r#""anastasia""#).is_err());
+
+    // Both patterns of an `allOf` are checked
+    let valid: checked::Validated =
+        serde_json::from_str(r#"{ "name": "ann", "tags": ["a"], "code": "A1" }"#).unwrap();
+    assert_eq!(valid.code.as_deref(), Some("A1"));
+    let err = serde_json::from_str::<checked::Validated>(
+        r#"{ "name": "ann", "tags": ["a"], "code": "A" }"#,
+    )
+    .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        r#"/code: "A" does not match "[0-9]$""#
+    );
+}
+
+mod newtypes {
+    use serde::{Deserialize, Serialize};
+
+    schemafy::schemafy!(
+        root: Post
+        newtypes: true
+        "tests/newtypes.json"
+    );
+}
+
+#[test]
+fn newtypes() {
+    use newtypes::{Count, Label, Post, Slug};
+    use std::convert::TryFrom;
+
+    let slug = Slug::new("abc".to_string()).unwrap();
+    assert_eq!(&*slug, "abc");
+    assert_eq!(slug.clone().into_inner(), "abc");
+    assert!(Slug::new("ABC".to_string()).is_err());
+    assert!(Count::try_from(0).is_err());
+    assert_eq!(*Count::try_from(2).unwrap(), 2);
+    let _: Label = String::new();
+
+    let post: Post = serde_json::from_str(r#"{ "slug": "abc", "count": 3 }"#).unwrap();
+    assert_eq!(post.slug, slug);
+    assert_eq!(
+        serde_json::to_value(&post).unwrap(),
+        serde_json::json!({ "slug": "abc", "count": 3, "note": null })
+    );
+    assert!(serde_json::from_str::<Post>(r#"{ "slug": "abc", "count": 0 }"#).is_err());
+}
+
+mod inheritance {
+    use serde::{Deserialize, Serialize};
+
+    schemafy::schemafy!(
+        root: Kennel
+        validate: true
+        inherit: true
+        "tests/inheritance.json"
+    );
+}
+
+#[test]
+fn inheritance() {
+    use inheritance::{Animal, Dog, Kennel, Named, Puppy};
+    use schemafy_core::validate::Validate;
+
+    let json = serde_json::json!({
+        "pets": [{ "name": "Rex", "age": 3, "breed": "beagle" }]
+    });
+    let kennel: Kennel = serde_json::from_value(json.clone()).unwrap();
+    let dog: &Dog = &kennel.pets.as_ref().unwrap()[0];
+    let _: &Animal = &dog.base;
+    assert_eq!(dog.name, "Rex");
+    assert_eq!(dog.breed, "beagle");
+    assert_eq!(serde_json::to_value(&kennel).unwrap(), json);
+    assert_eq!(kennel.validate(), Ok(()));
+    assert!(serde_json::from_value::<Dog>(serde_json::json!({ "name": "Rex" })).is_err());
+
+    let puppy: Puppy =
+        serde_json::from_value(serde_json::json!({ "name": "", "breed": "pug", "toy": "ball" }))
+            .unwrap();
+    assert_eq!((puppy.breed.as_str(), puppy.toy.as_deref()), ("pug", Some("ball")));
+    let errors = puppy.validate().unwrap_err();
+    assert_eq!(errors.iter().next().unwrap().pointer(), "/name");
+
+    // Redefining a property of the base copies the fields instead
+    let Named { name: _, age: _ } = serde_json::from_str(r#"{ "name": "Rex" }"#).unwrap();
+}
+
+mod formats {
+    use serde::{Deserialize, Serialize};
+
+    schemafy::schemafy!(
+        root: Account
+        "tests/formats.json"
+    );
+
+    pub mod ip {
+        use serde::{Deserialize, Serialize};
+
+        schemafy::schemafy!(
+            root: Account
+            formats: [ip]
+            "tests/formats.json"
+        );
+    }
+
+    #[cfg(all(feature = "chrono", feature = "uuid", feature = "url"))]
+    pub mod crates {
+        use serde::{Deserialize, Serialize};
+
+        schemafy::schemafy!(
+            root: Account
+            formats: [chrono, uuid, url, ip]
+            "tests/formats.json"
+        );
+    }
+
+    #[cfg(feature = "time")]
+    pub mod time {
+        use serde::{Deserialize, Serialize};
+
+        schemafy::schemafy!(
+            root: Account
+            formats: [time]
+            "tests/formats.json"
+        );
+    }
+}
+
+#[test]
+fn formats() {
+    let json = serde_json::json!({
+        "id": "67e55044-10b1-426f-9247-bb680e5fe0c8",
+        "created": "2020-01-02T03:04:05+01:00",
+        "birthday": "1990-12-31",
+        "homepage": "https://example.com/",
+        "address": "127.0.0.1",
+        "loopback": "::1",
+        "email": "ann@example.com",
+        "logins": ["2020-01-02T03:04:05Z"]
+    });
+
+    // Enabling the features of the crates does not change the types without `formats`
+    let account: formats::Account = serde_json::from_value(json.clone()).unwrap();
+    assert_eq!(serde_json::to_value(&account).unwrap(), json);
+    let _: String = account.id;
+    let _: String = account.created;
+    let _: Option<String> = account.address;
+
+    let account: formats::ip::Account = serde_json::from_value(json.clone()).unwrap();
+    assert_eq!(serde_json::to_value(&account).unwrap(), json);
+    let _: Option<std::net::Ipv4Addr> = account.address;
+    let _: Option<std::net::Ipv6Addr> = account.loopback;
+    let _: String = account.created;
+
+    #[cfg(all(feature = "chrono", feature = "uuid", feature = "url"))]
+    {
+        use schemafy_core::chrono::{DateTime, FixedOffset, NaiveDate};
+        let account: formats::crates::Account = serde_json::from_value(json.clone()).unwrap();
+        assert_eq!(serde_json::to_value(&account).unwrap(), json);
+        let _: DateTime<FixedOffset> = account.created;
+        let _: Option<NaiveDate> = account.birthday;
+        let _: Option<Vec<DateTime<FixedOffset>>> = account.logins;
+        let _: schemafy_core::uuid::Uuid = account.id;
+        let _: Option<schemafy_core::url::Url> = account.homepage;
+        let _: Option<String> = account.email;
+    }
+    #[cfg(feature = "time")]
+    {
+        use schemafy_core::time::{Date, OffsetDateTime};
+        let account: formats::time::Account = serde_json::from_value(json.clone()).unwrap();
+        assert_eq!(serde_json::to_value(&account).unwrap(), json);
+        let _: OffsetDateTime = account.created;
+        let _: Option<Date> = account.birthday;
+        // `time` types are only used for properties
+        let _: Option<Vec<String>> = account.logins;
+    }
+}
+
+schemafy::schemafy!(
+    root: Integers
+    infer_integers: true
+    "tests/integers.json"
+);
+
+#[test]
+fn infer_integers() {
+    let integers: Integers = serde_json::from_str(
+        r#"{
+            "byte": 255,
+            "small": -100,
+            "count": 0,
+            "port": 65535,
+            "id": -1,
+            "big": 18446744073709551615,
+            "huge": -100000000000000000000,
+            "plain": 1
+        }"#,
+    )
+    .unwrap();
+    let _: u8 = integers.byte;
+    let _: i8 = integers.small;
+    let _: u64 = integers.count;
+    let _: u16 = integers.port;
+    let _: i32 = integers.id;
+    assert_eq!(integers.big, u64::MAX);
+    assert_eq!(integers.huge, -100_000_000_000_000_000_000i128);
+    let _: i64 = integers.plain;
+}
+
+mod numbers {
+    use serde::{Deserialize, Serialize};
+
+    schemafy::schemafy!(
+        root: Invoice
+        numbers: number
+        "tests/numbers.json"
+    );
+
+    pub mod defaults {
+        use serde::{Deserialize, Serialize};
+
+        schemafy::schemafy!(
+            root: Invoice
+            "tests/numbers.json"
+        );
+    }
+
+    pub mod per_property {
+        use serde::{Deserialize, Serialize};
+
+        schemafy::schemafy!(
+            root: Invoice
+            decimals: number
+            "tests/numbers.json"
+        );
+    }
+
+    #[cfg(feature = "decimal")]
+    pub mod decimal {
+        use serde::{Deserialize, Serialize};
+
+        schemafy::schemafy!(
+            root: Invoice
+            numbers: decimal
+            "tests/numbers.json"
+        );
+    }
+
+    #[cfg(feature = "decimal")]
+    pub mod per_property_decimal {
+        use serde::{Deserialize, Serialize};
+
+        schemafy::schemafy!(
+            root: Invoice
+            decimals: decimal
+            "tests/numbers.json"
+        );
+    }
+}
+
+#[test]
+fn numbers() {
+    use schemafy_core::number::Number;
+
+    let json = r#"{"price":19.99,"ratio":0.5}"#;
+    let invoice: numbers::Invoice = serde_json::from_str(json).unwrap();
+    let _: Number = invoice.price.clone();
+    let _: Option<Number> = invoice.ratio.clone();
+    assert_eq!(serde_json::to_string(&invoice).unwrap(), json);
+
+    // Enabling the features of the crates does not change the types without `decimals`
+    let invoice: numbers::defaults::Invoice = serde_json::from_str(json).unwrap();
+    let _: f64 = invoice.price;
+    let _: Option<f64> = invoice.ratio;
+
+    let invoice: numbers::per_property::Invoice = serde_json::from_str(json).unwrap();
+    let _: Number = invoice.price.clone();
+    let _: Option<f64> = invoice.ratio;
+    assert_eq!(serde_json::to_string(&invoice).unwrap(), json);
+
+    #[cfg(feature = "decimal")]
+    {
+        use schemafy_core::rust_decimal::Decimal;
+
+        let invoice: numbers::per_property_decimal::Invoice = serde_json::from_str(json).unwrap();
+        assert_eq!(invoice.price, "19.99".parse::<Decimal>().unwrap());
+        let _: Option<f64> = invoice.ratio;
+        assert_eq!(serde_json::to_string(&invoice).unwrap(), json);
+
+        // Strings keep every digit without `arbitrary_precision`
+        let invoice: numbers::decimal::Invoice =
+            serde_json::from_str(r#"{"price":"12345678901234.123456789","ratio":0.1}"#).unwrap();
+        assert_eq!(
+            invoice.price,
+            "12345678901234.123456789".parse::<Decimal>().unwrap()
+        );
+        assert_eq!(invoice.ratio, Some("0.1".parse::<Decimal>().unwrap()));
+    }
+
+    #[cfg(feature = "arbitrary_precision")]
+    {
+        let json = r#"{"price":1.5,"ratio":1234567890.12345678901234567890}"#;
+        let invoice: numbers::Invoice = serde_json::from_str(json).unwrap();
+        assert_eq!(serde_json::to_string(&invoice).unwrap(), json);
+    }
+    #[cfg(all(feature = "decimal", feature = "arbitrary_precision"))]
+    {
+        let json = r#"{"price":12345678901234.123456789,"ratio":0.1}"#;
+        let invoice: numbers::decimal::Invoice = serde_json::from_str(json).unwrap();
+        assert_eq!(serde_json::to_string(&invoice).unwrap(), json);
+    }
+}
+
+schemafy::schemafy!(
+    root: Tuples
+    validate: true
+    "tests/tuples.json"
+);
+
+#[test]
+fn tuples() {
+    use schemafy_core::validate::Validate;
+
+    let json = serde_json::json!({
+        "position": [1.5, 2.0],
+        "entry": ["a", { "count": 1 }],
+        "open": ["b", 2, true],
+        "rgb": [0, 128, 255]
+    });
+    let tuples: Tuples = serde_json::from_value(json.clone()).unwrap();
+    assert_eq!(tuples.position, Some((1.5, 2.0)));
+    let entry: Entry = tuples.entry.clone().unwrap();
+    let _: EntryItem1 = entry.1;
+    let _: Option<Vec<serde_json::Value>> = tuples.open.clone();
+    assert_eq!(tuples.rgb, Some([0, 128, 255]));
+    assert_eq!(serde_json::to_value(&tuples).unwrap(), json);
+    assert_eq!(tuples.validate(), Ok(()));
+
+    assert!(serde_json::from_value::<Tuples>(serde_json::json!({ "position": [1.5] })).is_err());
+    assert!(
+        serde_json::from_value::<Tuples>(serde_json::json!({ "rgb": [1, 2, 3, 4] })).is_err()
+    );
+    let invalid: Tuples = serde_json::from_value(serde_json::json!({ "rgb": [0, 256, 0] })).unwrap();
+    let errors = invalid.validate().unwrap_err();
+    assert_eq!(errors.iter().next().unwrap().pointer(), "/rgb/1");
+}
+
+schemafy::schemafy!(
+    root: PatternProperties
+    validate: true
+    "tests/pattern-properties.json"
+);
+
+#[test]
+fn pattern_properties() {
+    use schemafy_core::validate::Validate;
+    use std::collections::BTreeMap;
+
+    let json = serde_json::json!({
+        "headers": { "x-trace": "abc" },
+        "counts": { "apples": 3, "LABEL": "fruit" },
+        "limits": { "soft": 1, "hard": 2 },
+        "extensions": { "x-one": "1", "other": [true] }
+    });
+    let properties: PatternProperties = serde_json::from_value(json.clone()).unwrap();
+    let _: &Option<BTreeMap<String, String>> = &properties.headers;
+    let counts = properties.counts.clone().unwrap();
+    assert_eq!(
+        counts["apples"],
+        PatternPropertiesCountsValue::Integer(3)
+    );
+    let limits = properties.limits.clone().unwrap();
+    assert_eq!(limits[&PatternPropertiesLimitsKey::Hard], 2);
+    let _: &Option<BTreeMap<String, serde_json::Value>> = &properties.extensions;
+    assert_eq!(serde_json::to_value(&properties).unwrap(), json);
+    assert_eq!(properties.validate(), Ok(()));
+
+    assert!(serde_json::from_value::<PatternProperties>(
+        serde_json::json!({ "limits": { "medium": 1 } })
+    )
+    .is_err());
+    let invalid: PatternProperties =
+        serde_json::from_value(serde_json::json!({ "counts": { "pears": -1 } })).unwrap();
+    let errors = invalid.validate().unwrap_err();
+    assert_eq!(errors.iter().next().unwrap().pointer(), "/counts/pears");
+}
+
+schemafy::schemafy!(
+    root: ExtraProperties
+    validate: true
+    "tests/extra-properties.json"
+);
+
+#[test]
+fn extra_properties() {
+    use schemafy_core::validate::Validate;
+
+    let json = serde_json::json!({ "name": "counts", "apples": 3, "pears": 4 });
+    let counts: ExtraProperties = serde_json::from_value(json.clone()).unwrap();
+    assert_eq!(counts.name, "counts");
+    assert_eq!(counts.extra["pears"], 4);
+    assert_eq!(serde_json::to_value(&counts).unwrap(), json);
+    assert_eq!(counts.validate(), Ok(()));
+    assert!(serde_json::from_value::<ExtraProperties>(
+        serde_json::json!({ "name": "counts", "apples": "3" })
+    )
+    .is_err());
+
+    let json = serde_json::json!({ "extra": true, "other": [null] });
+    let open: Open = serde_json::from_value(json.clone()).unwrap();
+    assert_eq!(open.extra, Some(true));
+    assert_eq!(open.extra_["other"], serde_json::json!([null]));
+    assert_eq!(serde_json::to_value(&open).unwrap(), json);
+
+    let Fixed { id: _ } = Fixed::default();
+}
+
+#[test]
+fn closed_objects() {
+    let fixed: Fixed = serde_json::from_str(r#"{"id":1}"#).unwrap();
+    assert_eq!(fixed.id, Some(1));
+    assert!(serde_json::from_str::<Fixed>(r#"{"id":1,"name":"one"}"#).is_err());
+
+    let identified: Identified = serde_json::from_str(r#"{"id":1}"#).unwrap();
+    assert_eq!(identified.id, 1);
+    assert!(serde_json::from_str::<Identified>(r#"{"id":1,"name":"one"}"#).is_err());
+}
+
+schemafy::schemafy!(
+    root: MutualRecursion
+    "tests/mutual-recursion.json"
+);
+
+#[test]
+fn mutual_recursion() {
+    let m: MutualRecursion = serde_json::from_str(
+        r#"{
+            "expression": { "operator": "+", "left": 1, "right": { "operator": "*", "left": 2, "right": 3 } },
+            "department": { "name": "a", "head": { "name": "b", "department": { "name": "c" } } }
+        }"#,
+    )
+    .unwrap();
+    let binary = match m.expression {
+        Expression::Binary(binary) => binary,
+        expression => panic!("{:?}", expression),
+    };
+    assert_eq!(binary.left, Expression::Number(1.0));
+    assert!(matches!(binary.right, Expression::Binary(_)));
+    let head = m.department.unwrap().head.unwrap();
+    assert_eq!(head.department.unwrap().name, "c");
+    assert!(head.reports.is_none());
+}
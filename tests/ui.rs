@@ -0,0 +1,10 @@
+//! Checks the compile errors of the `schemafy!` macro.
+//!
+//! trybuild compiles the cases as a crate of its own in `target/tests/trybuild`, which the paths
+//! to the schemas are relative to.
+
+#[test]
+fn ui() {
+    let cases = trybuild::TestCases::new();
+    cases.compile_fail("tests/ui/*.rs");
+}
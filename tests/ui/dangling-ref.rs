@@ -0,0 +1,6 @@
+schemafy::schemafy!(
+    root: DanglingRef
+    "../../../../tests/ui/dangling-ref.json"
+);
+
+fn main() {}
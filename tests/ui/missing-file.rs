@@ -0,0 +1,6 @@
+schemafy::schemafy!(
+    root: Missing
+    "../../../../tests/ui/missing.json"
+);
+
+fn main() {}
@@ -0,0 +1,6 @@
+schemafy::schemafy!(
+    root: ParseError
+    "../../../../tests/ui/parse-error.json"
+);
+
+fn main() {}